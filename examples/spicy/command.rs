@@ -1,5 +1,5 @@
 use crossbeam_channel::{unbounded, Receiver, Sender};
-use flyPath::FlyPath;
+use flyPath::{FlyPath, FlyPathBuilder};
 use std::thread::{self, sleep};
 use std::time::Duration;
 use wg_2024::{
@@ -196,8 +196,7 @@ fn setup(
     let (d1_command_send, d1_command_recv) = unbounded();
     let (d2_command_send, d2_command_recv) = unbounded();
 
-    let d1 = FlyPath::new_with_mode(
-        flyPath::FlyPathModes::Spicy(flyPath::FlyPathThemes::Bloodborne),
+    let d1 = FlyPathBuilder::new(
         1,
         d1_event_send,
         d1_command_recv,
@@ -206,8 +205,11 @@ fn setup(
         //     .into_iter()
         //     .collect(),
         vec![(10, c1_send.clone())].into_iter().collect(),
-        pdr_d1,
-    );
+    )
+    .pdr(pdr_d1)
+    .theme(flyPath::FlyPathThemes::Bloodborne)
+    .build()
+    .unwrap();
     let d2 = FlyPath::new(
         2,
         d2_event_send,
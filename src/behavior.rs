@@ -0,0 +1,204 @@
+use crate::flypath::FlyPath;
+use crate::stats::StatsHandle;
+use rand::rngs::StdRng;
+use rand::Rng;
+use std::fmt;
+use wg_2024::controller::{DroneCommand, DroneEvent};
+use wg_2024::network::NodeId;
+use wg_2024::packet::{FloodRequest, NackType, Packet};
+
+#[cfg(feature = "modes")]
+use crate::command::MessageVerbosity;
+#[cfg(feature = "modes")]
+use crate::flypath::FlyPathModes;
+#[cfg(feature = "modes")]
+use crate::messages::Messages;
+#[cfg(feature = "modes")]
+use crate::template::TemplateValues;
+
+#[cfg(feature = "modes")]
+mod brainrot;
+#[cfg(feature = "modes")]
+mod spicy;
+
+#[cfg(feature = "modes")]
+pub use brainrot::BrainRotBehavior;
+#[cfg(feature = "modes")]
+pub use spicy::SpicyBehavior;
+
+/// How a `FlyPath` drone reacts to commands and packets.
+///
+/// The drone always follows the protocol for routing, nacks and flood responses; the behavior is asked
+/// at every decision point and can change the packet, send themed messages or refuse a command.
+/// Every hook has a default implementation that follows the protocol, so a behavior only overrides
+/// the hooks it needs. `FlyPathModes` maps to `DefaultBehavior`, `SpicyBehavior` and `BrainRotBehavior`.
+///
+/// # Example
+/// ```ignore
+/// #[derive(Debug, Clone)]
+/// struct NeverCrash;
+///
+/// impl DroneBehavior for NeverCrash {
+///     fn on_command(&mut self, _ctx: &mut DroneContext, cmd: &DroneCommand) -> bool {
+///         !matches!(cmd, DroneCommand::Crash)
+///     }
+///
+///     fn clone_box(&self) -> Box<dyn DroneBehavior> {
+///         Box::new(self.clone())
+///     }
+/// }
+///
+/// let drone = FlyPathBuilder::new(1, controller_send, controller_recv, packet_recv, packet_send)
+///     .behavior(NeverCrash)
+///     .build()?;
+/// ```
+pub trait DroneBehavior: Send + fmt::Debug {
+    /// Called for every `DroneCommand` before it is applied, returns `false` to ignore the command
+    fn on_command(&mut self, _ctx: &mut DroneContext, _cmd: &DroneCommand) -> bool {
+        true
+    }
+
+    /// Called with the flood request already incremented with the drone, before it is forwarded or answered
+    fn on_flood_request(&mut self, _ctx: &mut DroneContext, _flood_request: &mut FloodRequest) {}
+
+    /// Called for every valid packet that is not a flood request, before the drop decision
+    fn on_packet(&mut self, _ctx: &mut DroneContext, _packet: &mut Packet) {}
+
+    /// Decide if a `MsgFragment` is dropped, by default with probability equal to the pdr of the link
+    /// to the next hop
+    fn should_drop(&mut self, ctx: &mut DroneContext, packet: &Packet) -> bool {
+        ctx.link_pdr(packet.routing_header.next_hop()) > ctx.rng().gen_range(0.0..1.0)
+    }
+
+    /// Called before a packet is sent to the next hop, the hop index already points to the next hop
+    fn on_forward(&mut self, _ctx: &mut DroneContext, _packet: &mut Packet) {}
+
+    /// Called before `DroneEvent::PacketSent` is sent for a fragment
+    fn on_packet_sent(&mut self, _ctx: &mut DroneContext, _packet: &Packet) {}
+
+    /// Called before `DroneEvent::PacketDropped` is sent for a fragment that has been nacked
+    fn on_packet_dropped(&mut self, _ctx: &mut DroneContext, _packet: &Packet) {}
+
+    /// Clone the behavior, needed because `FlyPath` is `Clone`
+    fn clone_box(&self) -> Box<dyn DroneBehavior>;
+}
+
+impl Clone for Box<dyn DroneBehavior> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+/// The drone behaves as described in the protocol file
+#[derive(Debug, Clone, Default)]
+pub struct DefaultBehavior;
+
+impl DroneBehavior for DefaultBehavior {
+    fn clone_box(&self) -> Box<dyn DroneBehavior> {
+        Box::new(self.clone())
+    }
+}
+
+/// Access to the drone given to the `DroneBehavior` hooks
+pub struct DroneContext<'a> {
+    drone: &'a mut FlyPath,
+}
+
+impl<'a> DroneContext<'a> {
+    pub(crate) fn new(drone: &'a mut FlyPath) -> Self {
+        Self { drone }
+    }
+
+    pub fn id(&self) -> NodeId {
+        self.drone.id
+    }
+
+    pub fn pdr(&self) -> f32 {
+        self.drone.pdr
+    }
+
+    pub fn set_pdr(&mut self, pdr: f32) {
+        self.drone.pdr = pdr;
+    }
+
+    /// Pdr of the link to `neighbor` set with `FlyPathCommand::SetLinkPdr`, otherwise the pdr of the drone
+    pub fn link_pdr(&self, neighbor: Option<NodeId>) -> f32 {
+        neighbor
+            .and_then(|neighbor| self.drone.link_pdr.get(&neighbor).copied())
+            .unwrap_or(self.drone.pdr)
+    }
+
+    /// The random source of the drone, use it for every random decision so that seeded drones are reproducible
+    pub fn rng(&mut self) -> &mut StdRng {
+        &mut self.drone.rng
+    }
+
+    pub fn stats(&self) -> &StatsHandle {
+        &self.drone.stats
+    }
+
+    /// `true` if `id` is a neighbor of the drone
+    pub fn is_neighbor(&self, id: NodeId) -> bool {
+        self.drone.packet_send.contains_key(&id)
+    }
+
+    /// The nack sent for the fragment, only inside `DroneBehavior::on_packet_dropped`
+    pub fn nack_type(&self) -> Option<&NackType> {
+        self.drone.dropped_nack.as_ref()
+    }
+
+    /// Send an event to the controller, following the controller failure policy if it is unreachable
+    pub fn send_event(&mut self, event: DroneEvent) -> bool {
+        self.drone.send_event(event)
+    }
+
+    #[cfg(feature = "modes")]
+    pub fn messages(&self) -> &Messages {
+        &self.drone.messages
+    }
+
+    /// Pick a message of `mode` for the event, command or BrainRot action `key` following the
+    /// `SelectionStrategy` of its list and the selection state of the drone, fill its placeholders and
+    /// send it to the controller. `values` come from the command or the packet that triggered the message,
+    /// see `TemplateValues::from_command` and `TemplateValues::from_packet`; the id, the pdr, the nack
+    /// being sent and the fragments forwarded by the drone fill the values left empty.
+    /// Returns `false` if there is no message, the verbosity of the drone excludes it or it could not be sent.
+    #[cfg(feature = "modes")]
+    pub fn send_flypath_message(
+        &mut self,
+        mode: &FlyPathModes,
+        key: &str,
+        values: TemplateValues,
+    ) -> bool {
+        let drone = &mut *self.drone;
+        let allowed = match drone.verbosity {
+            MessageVerbosity::Off => false,
+            MessageVerbosity::Commands => Messages::is_command_key(key),
+            MessageVerbosity::All => true,
+        };
+        if !allowed {
+            return false;
+        }
+        let values = TemplateValues {
+            node_id: values.node_id.or(Some(drone.id)),
+            pdr: values.pdr.or(Some(drone.pdr)),
+            nack_type: values.nack_type.or(drone.dropped_nack),
+            packets_forwarded: values
+                .packets_forwarded
+                .or_else(|| Some(drone.stats.fragments_forwarded())),
+            ..values
+        };
+        let message = drone.messages.select_flypath_message(
+            mode,
+            key,
+            drone.id,
+            &values,
+            &mut drone.selector,
+            &mut drone.rng,
+        );
+        match message {
+            Some(message) => drone.send_flypath_message(message),
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use super::{DroneBehavior, DroneContext};
+use crate::flypath::FlyPathModes;
+use crate::template::TemplateValues;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use wg_2024::controller::DroneCommand;
+use wg_2024::network::SourceRoutingHeader;
+use wg_2024::packet::{FloodRequest, Packet, PacketType};
+
+/// The drone behaves erratically: it refuses commands, changes its pdr, shuffles paths, invalidates
+/// fragments and flood requests, and reports every action with a BrainRot message
+#[derive(Debug, Clone, Default)]
+pub struct BrainRotBehavior;
+
+impl BrainRotBehavior {
+    // Record the action and send its message, `values` come from the command or the packet of the action
+    fn action(&self, ctx: &mut DroneContext, action: &str, values: TemplateValues) {
+        ctx.stats().record_brainrot(action);
+        ctx.send_flypath_message(&FlyPathModes::BrainRot, action, values);
+    }
+
+    // 50/50 apply the command, reporting `action` or `refused_action`
+    fn maybe_apply(
+        &self,
+        ctx: &mut DroneContext,
+        cmd: &DroneCommand,
+        action: &str,
+        refused_action: &str,
+    ) -> bool {
+        let should_apply = 50 > ctx.rng().gen_range(0..100);
+        let values = TemplateValues::from_command(cmd);
+        if should_apply {
+            self.action(ctx, action, values);
+        } else {
+            self.action(ctx, refused_action, values);
+        }
+        should_apply
+    }
+
+    // Shuffle the hops after the next one
+    fn change_hops_randomly(ctx: &mut DroneContext, routing_header: &mut SourceRoutingHeader) {
+        let hop_index = routing_header.hop_index;
+        // Ensure we have enough hops to modify
+        if hop_index + 1 < routing_header.hops.len() {
+            // Extract the portion of hops to be randomized
+            let remaining_hops = &mut routing_header.hops[(hop_index + 1)..];
+
+            // Shuffle the remaining hops
+            remaining_hops.shuffle(ctx.rng());
+        }
+    }
+}
+
+impl DroneBehavior for BrainRotBehavior {
+    fn on_command(&mut self, ctx: &mut DroneContext, cmd: &DroneCommand) -> bool {
+        match cmd {
+            DroneCommand::Crash => {
+                let should_crash = 50 < ctx.rng().gen_range(0..100);
+                if should_crash {
+                    self.action(ctx, "Crash", TemplateValues::default());
+                } else {
+                    self.action(ctx, "NoCrash", TemplateValues::default());
+                }
+                should_crash
+            }
+            DroneCommand::AddSender(_, _) => self.maybe_apply(ctx, cmd, "AddSender", "NoAddSender"),
+            DroneCommand::RemoveSender(_) => {
+                self.maybe_apply(ctx, cmd, "RemoveSender", "NoRemoveSender")
+            }
+            DroneCommand::SetPacketDropRate(_) => {
+                self.maybe_apply(ctx, cmd, "SetPacketDropRate", "NoSetPacketDropRate")
+            }
+        }
+    }
+
+    fn on_flood_request(&mut self, ctx: &mut DroneContext, flood_request: &mut FloodRequest) {
+        let should_change_initiator_or_path_trace = 50 > ctx.rng().gen_range(0..100);
+        if should_change_initiator_or_path_trace {
+            self.action(ctx, "ChangeInitiatorId", TemplateValues::default());
+            flood_request.initiator_id = ctx.rng().gen_range(0..31);
+        } else {
+            self.action(ctx, "ShufflePathTrace", TemplateValues::default());
+            flood_request.path_trace.shuffle(ctx.rng());
+        }
+    }
+
+    fn on_packet(&mut self, ctx: &mut DroneContext, _packet: &mut Packet) {
+        let pdr = ctx.rng().gen_range(0.0..1.0);
+        ctx.set_pdr(pdr);
+    }
+
+    fn on_forward(&mut self, ctx: &mut DroneContext, packet: &mut Packet) {
+        // Change path or invalidate the content
+        let should_change_path_or_invalidate_content = 50 > ctx.rng().gen_range(0..100);
+        if should_change_path_or_invalidate_content {
+            Self::change_hops_randomly(ctx, &mut packet.routing_header);
+            self.action(ctx, "PacketSent", TemplateValues::from_packet(packet));
+        } else if let PacketType::MsgFragment(fragment) = &mut packet.pack_type {
+            fragment.data.shuffle(ctx.rng());
+            self.action(ctx, "PacketSentPANIC", TemplateValues::from_packet(packet));
+        }
+    }
+
+    fn on_packet_dropped(&mut self, ctx: &mut DroneContext, packet: &Packet) {
+        self.action(ctx, "PacketDropped", TemplateValues::from_packet(packet));
+    }
+
+    fn clone_box(&self) -> Box<dyn DroneBehavior> {
+        Box::new(self.clone())
+    }
+}
@@ -0,0 +1,52 @@
+use super::{DroneBehavior, DroneContext};
+use crate::flypath::{FlyPathModes, FlyPathThemes};
+use crate::messages::Messages;
+use crate::template::TemplateValues;
+use wg_2024::controller::DroneCommand;
+use wg_2024::packet::Packet;
+
+/// Follows the protocol and sends a message of the theme for every command, sent and dropped fragment
+#[derive(Debug, Clone)]
+pub struct SpicyBehavior {
+    mode: FlyPathModes,
+}
+
+impl SpicyBehavior {
+    pub fn new(theme: FlyPathThemes) -> Self {
+        Self {
+            mode: FlyPathModes::Spicy(theme),
+        }
+    }
+}
+
+impl DroneBehavior for SpicyBehavior {
+    fn on_command(&mut self, ctx: &mut DroneContext, cmd: &DroneCommand) -> bool {
+        ctx.send_flypath_message(
+            &self.mode,
+            Messages::drone_command_to_string(cmd),
+            TemplateValues::from_command(cmd),
+        );
+        true
+    }
+
+    fn on_packet_sent(&mut self, ctx: &mut DroneContext, packet: &Packet) {
+        // the packet has already been sent, its current hop is the neighbor that received it
+        let values = TemplateValues {
+            next_hop: packet.routing_header.current_hop(),
+            ..TemplateValues::from_packet(packet)
+        };
+        ctx.send_flypath_message(&self.mode, "PacketSent", values);
+    }
+
+    fn on_packet_dropped(&mut self, ctx: &mut DroneContext, packet: &Packet) {
+        ctx.send_flypath_message(
+            &self.mode,
+            "PacketDropped",
+            TemplateValues::from_packet(packet),
+        );
+    }
+
+    fn clone_box(&self) -> Box<dyn DroneBehavior> {
+        Box::new(self.clone())
+    }
+}
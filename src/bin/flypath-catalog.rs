@@ -0,0 +1,321 @@
+use flyPath::{
+    FlyPathError, FlyPathModes, FlyPathThemes, MessageSelector, Messages, TemplateValues,
+    BRAINROT_KEYS, FLYPATH_SINGLE_FRAGMENT_LEN, SPICY_KEYS,
+};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::ExitCode;
+use std::{env, fs};
+use wg_2024::packet::NackType;
+
+const USAGE: &str = "Usage: flypath-catalog <command> [--catalog <path>]
+
+Commands:
+  lint                                    check the catalog with the rules used by the drones
+  list-themes                             list the themes of the catalog
+  preview <theme> <event> [--count <n>] [--language <language>]
+                                          render random messages of a theme (or \"brainrot\") for an event
+  stats                                   coverage of the events of every theme and length of the messages
+  fmt [--write]                           print the catalog in canonical order, or rewrite the file
+
+The catalog is a JSON or TOML file, or a directory of theme packs (default: the catalog embedded in the crate).";
+
+// Values used to render the placeholders of the previewed messages
+fn sample_values() -> TemplateValues {
+    TemplateValues {
+        node_id: Some(1),
+        neighbor: Some(2),
+        pdr: Some(0.1),
+        session_id: Some(42),
+        fragment_index: Some(0),
+        next_hop: Some(3),
+        nack_type: Some(NackType::Dropped),
+        packets_forwarded: Some(17),
+    }
+}
+
+fn main() -> ExitCode {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let catalog = match take_option(&mut args, "--catalog") {
+        Ok(catalog) => catalog,
+        Err(e) => return usage_error(&e),
+    };
+    let catalog = catalog.as_deref();
+    if args.is_empty() {
+        return usage_error("missing command");
+    }
+    let command = args.remove(0);
+
+    let result = match command.as_str() {
+        "lint" => lint(catalog, &args),
+        "list-themes" => list_themes(catalog, &args),
+        "preview" => preview(catalog, args),
+        "stats" => stats(catalog, &args),
+        "fmt" => fmt(catalog, args),
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        _ => return usage_error(&format!("unknown command '{}'", command)),
+    };
+    match result {
+        Ok(code) => code,
+        Err(CliError::Usage(message)) => usage_error(&message),
+        Err(CliError::Catalog(e)) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+        Err(CliError::Format(message)) => {
+            eprintln!("error: failed to format the catalog: {}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+enum CliError {
+    Usage(String),
+    Catalog(FlyPathError),
+    // the catalog has been loaded but could not be serialized
+    Format(String),
+}
+
+impl From<FlyPathError> for CliError {
+    fn from(e: FlyPathError) -> Self {
+        CliError::Catalog(e)
+    }
+}
+
+fn usage_error(message: &str) -> ExitCode {
+    eprintln!("error: {}\n\n{}", message, USAGE);
+    ExitCode::from(2)
+}
+
+// Remove `--name <value>` from `args` and return the value
+fn take_option(args: &mut Vec<String>, name: &str) -> Result<Option<String>, String> {
+    let Some(index) = args.iter().position(|arg| arg == name) else {
+        return Ok(None);
+    };
+    if index + 1 >= args.len() {
+        return Err(format!("missing value of {}", name));
+    }
+    let value = args.remove(index + 1);
+    args.remove(index);
+    Ok(Some(value))
+}
+
+// Remove the flag `name` from `args`, return `true` if it was there
+fn take_flag(args: &mut Vec<String>, name: &str) -> bool {
+    let len = args.len();
+    args.retain(|arg| arg != name);
+    args.len() != len
+}
+
+fn no_arguments(args: &[String]) -> Result<(), CliError> {
+    match args.first() {
+        Some(arg) => Err(CliError::Usage(format!("unexpected argument '{}'", arg))),
+        None => Ok(()),
+    }
+}
+
+// Load the catalog at `path`, or the embedded one, with the files of a directory that have been skipped
+fn load(path: Option<&str>) -> Result<(Messages, Vec<(String, FlyPathError)>), FlyPathError> {
+    let Some(path) = path else {
+        return Ok((Messages::default(), Vec::new()));
+    };
+    if Path::new(path).is_dir() {
+        let packs = Messages::load_from_dir(path)?;
+        Ok((packs.messages, packs.errors))
+    } else {
+        Ok((Messages::load_from_file(path)?, Vec::new()))
+    }
+}
+
+fn lint(catalog: Option<&str>, args: &[String]) -> Result<ExitCode, CliError> {
+    no_arguments(args)?;
+    let (messages, skipped) = load(catalog)?;
+    for (path, e) in &skipped {
+        match e {
+            FlyPathError::InvalidCatalog(report) => {
+                for issue in report.errors() {
+                    println!("error: {}: {}", path, issue);
+                }
+            }
+            e => println!("error: {}: {}", path, e),
+        }
+    }
+    let report = messages.validate();
+    for issue in report.errors() {
+        println!("error: {}", issue);
+    }
+    for issue in report.warnings() {
+        println!("warning: {}", issue);
+    }
+    let errors = skipped.len() + report.errors().count();
+    println!(
+        "{}: {} errors, {} warnings",
+        catalog.unwrap_or("default"),
+        errors,
+        report.warnings().count()
+    );
+    Ok(if errors == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+fn list_themes(catalog: Option<&str>, args: &[String]) -> Result<ExitCode, CliError> {
+    no_arguments(args)?;
+    let (messages, _) = load(catalog)?;
+    for theme in messages.themes() {
+        let custom = matches!(theme, FlyPathThemes::Custom(_));
+        println!("{}{}", theme, if custom { " (custom)" } else { "" });
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn preview(catalog: Option<&str>, mut args: Vec<String>) -> Result<ExitCode, CliError> {
+    let count = match take_option(&mut args, "--count").map_err(CliError::Usage)? {
+        Some(count) => count
+            .parse::<usize>()
+            .map_err(|_| CliError::Usage(format!("invalid count '{}'", count)))?,
+        None => 5,
+    };
+    let mut selector = match take_option(&mut args, "--language").map_err(CliError::Usage)? {
+        Some(language) => MessageSelector::with_language(&language),
+        None => MessageSelector::new(),
+    };
+    let [theme, event] = args.as_slice() else {
+        return Err(CliError::Usage(
+            "preview needs a theme and an event".to_string(),
+        ));
+    };
+    let (messages, _) = load(catalog)?;
+    let mode = if theme == "brainrot" {
+        FlyPathModes::BrainRot
+    } else {
+        FlyPathModes::Spicy(messages.theme(theme)?)
+    };
+
+    let values = sample_values();
+    let mut rng = rand::thread_rng();
+    for _ in 0..count {
+        let Some(message) = messages.select_message(&mode, event, &mut selector, &mut rng) else {
+            eprintln!("{} has no messages for '{}'", theme, event);
+            return Ok(ExitCode::FAILURE);
+        };
+        println!("{}", Messages::render(&message, &values));
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn stats(catalog: Option<&str>, args: &[String]) -> Result<ExitCode, CliError> {
+    no_arguments(args)?;
+    let (messages, _) = load(catalog)?;
+
+    // keys, lengths and fragments of the messages of every section, the brainrot section last.
+    // The messages are measured as the drones send them, with the placeholders filled and the header
+    let mut sections: BTreeMap<(bool, String), Vec<(String, usize, usize)>> = BTreeMap::new();
+    for line in messages.lines() {
+        let (section, mode) = match line.theme {
+            Some(theme) => {
+                let mode = FlyPathModes::Spicy(messages.theme(&theme)?);
+                ((false, theme), mode)
+            }
+            None => ((true, "brainrot".to_string()), FlyPathModes::BrainRot),
+        };
+        let mut values = sample_values();
+        if Messages::is_command_key(&line.key) {
+            values.session_id = None;
+        }
+        let message = Messages::flypath_message(&mode, &line.key, 1, &line.text, &values);
+        // a message too long for the drones is reported by `lint`, here it counts as several fragments
+        let fragments = message
+            .to_events()
+            .map_or(usize::MAX, |events| events.len());
+        sections
+            .entry(section)
+            .or_default()
+            .push((line.key, message.text.len(), fragments));
+    }
+
+    println!(
+        "{:<20} {:>8} {:>9} {:>9} {:>15}",
+        "section", "events", "messages", "max bytes", "multi-fragment"
+    );
+    for ((brainrot, name), lines) in &sections {
+        let keys: &[&str] = if *brainrot {
+            &BRAINROT_KEYS
+        } else {
+            &SPICY_KEYS
+        };
+        let covered = keys
+            .iter()
+            .filter(|key| lines.iter().any(|(line_key, ..)| line_key == *key))
+            .count();
+        let max = lines.iter().map(|(_, len, _)| *len).max().unwrap_or(0);
+        let long = lines
+            .iter()
+            .filter(|(.., fragments)| *fragments > 1)
+            .count();
+        println!(
+            "{:<20} {:>8} {:>9} {:>9} {:>15}",
+            name,
+            format!("{}/{}", covered, keys.len()),
+            lines.len(),
+            max,
+            long
+        );
+        let missing: Vec<&str> = keys
+            .iter()
+            .filter(|key| !lines.iter().any(|(line_key, ..)| line_key == *key))
+            .copied()
+            .collect();
+        if !missing.is_empty() {
+            println!("  missing: {}", missing.join(", "));
+        }
+    }
+    println!(
+        "\nLengths are measured with the placeholders filled as in preview. Messages that do not fit in one \
+         fragment with their header (at most {} bytes of text) are split across several fragments.",
+        FLYPATH_SINGLE_FRAGMENT_LEN
+    );
+    Ok(ExitCode::SUCCESS)
+}
+
+fn fmt(catalog: Option<&str>, mut args: Vec<String>) -> Result<ExitCode, CliError> {
+    let write = take_flag(&mut args, "--write");
+    no_arguments(&args)?;
+    match catalog {
+        Some(catalog) if Path::new(catalog).is_dir() => {
+            return Err(CliError::Usage(
+                "fmt needs a catalog file, not a directory".to_string(),
+            ))
+        }
+        None if write => {
+            return Err(CliError::Usage(
+                "fmt --write needs a catalog file".to_string(),
+            ))
+        }
+        _ => {}
+    }
+    let (messages, _) = load(catalog)?;
+    let formatted = if catalog.is_some_and(|catalog| catalog.ends_with(".toml")) {
+        toml::to_string(&messages).map_err(|e| CliError::Format(e.to_string()))?
+    } else {
+        let mut json =
+            serde_json::to_string_pretty(&messages).map_err(|e| CliError::Format(e.to_string()))?;
+        json.push('\n');
+        json
+    };
+    match catalog {
+        Some(catalog) if write => {
+            fs::write(catalog, formatted).map_err(|source| FlyPathError::Io {
+                path: catalog.to_string(),
+                source,
+            })?;
+        }
+        _ => print!("{}", formatted),
+    }
+    Ok(ExitCode::SUCCESS)
+}
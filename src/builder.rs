@@ -0,0 +1,334 @@
+use crate::behavior::{DefaultBehavior, DroneBehavior};
+use crate::command::{FlyPathCommand, FlyPathReply, MessageVerbosity};
+use crate::error::FlyPathError;
+use crate::flood_cache::{FloodCache, FloodCachePolicy};
+use crate::flypath::{ControllerFailurePolicy, FlyPath, FlyPathModes};
+use crate::flypath_message::FlyPathMessage;
+use crate::stats::StatsHandle;
+use crossbeam_channel::{Receiver, Sender};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+use wg_2024::controller::{DroneCommand, DroneEvent};
+use wg_2024::network::NodeId;
+use wg_2024::packet::Packet;
+
+#[cfg(feature = "modes")]
+use crate::flypath::FlyPathThemes;
+#[cfg(feature = "modes")]
+use crate::messages::Messages;
+#[cfg(feature = "modes")]
+use crate::selection::MessageSelector;
+#[cfg(feature = "modes")]
+use std::sync::Arc;
+
+/// Where the builder takes the message catalog from
+#[cfg(feature = "modes")]
+#[derive(Debug, Clone)]
+enum Catalog {
+    /// The catalog embedded in the crate
+    Embedded,
+    /// A catalog supplied by the caller
+    Messages(Messages),
+    /// A catalog file (JSON or TOML, see `Messages::load_from_file`) loaded from the path when `build` is called
+    Path(String),
+}
+
+/// Builder of a `FlyPath` drone.
+///
+/// The id and the channels are required, everything else is optional:
+/// - `pdr`: packet drop rate, `0.0` by default
+/// - `mode`/`theme`/`behavior`: `FlyPathModes::Default` by default
+/// - `messages`/`catalog_path`: the embedded catalog by default
+/// - `watch_catalog`: the catalog is reloaded only with `FlyPathCommand::ReloadCatalog` by default
+/// - `language`: none by default, the messages are in the fallback language of the catalog
+/// - `seed`: random source seeded from the OS by default
+/// - `flood_cache`: `FloodCachePolicy::default()`, an LRU cache of 1024 flood requests
+/// - `crash_deadline`: none by default, after a `Crash` the drone serves until all the neighbors disconnect
+/// - `controller_failure`: `ControllerFailurePolicy::Panic` by default
+/// - `commands`/`replies`: channels of the `FlyPathCommand`s and of their `FlyPathReply`s, none by default
+/// - `messages_channel`: none by default, the FlyPath messages are sent to the controller as `PacketSent`
+///
+/// # Example
+/// ```ignore
+/// let drone = FlyPathBuilder::new(1, controller_send, controller_recv, packet_recv, packet_send)
+///     .pdr(0.05)
+///     .theme(FlyPathThemes::Pingu)
+///     .seed(42)
+///     .build()?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct FlyPathBuilder {
+    id: NodeId,
+    controller_send: Sender<DroneEvent>,
+    controller_recv: Receiver<DroneCommand>,
+    packet_recv: Receiver<Packet>,
+    packet_send: HashMap<NodeId, Sender<Packet>>,
+    pdr: f32,
+    behavior: Box<dyn DroneBehavior>,
+    seed: Option<u64>,
+    flood_cache: FloodCachePolicy,
+    crash_deadline: Option<Duration>,
+    controller_failure: ControllerFailurePolicy,
+    flypath_recv: Receiver<FlyPathCommand>,
+    flypath_send: Option<Sender<FlyPathReply>>,
+    message_send: Option<Sender<FlyPathMessage>>,
+    #[cfg(feature = "modes")]
+    catalog: Catalog,
+    #[cfg(feature = "modes")]
+    catalog_watch: Option<Duration>,
+    #[cfg(feature = "modes")]
+    language: Option<String>,
+}
+
+impl FlyPathBuilder {
+    pub fn new(
+        id: NodeId,
+        controller_send: Sender<DroneEvent>,
+        controller_recv: Receiver<DroneCommand>,
+        packet_recv: Receiver<Packet>,
+        packet_send: HashMap<NodeId, Sender<Packet>>,
+    ) -> Self {
+        Self {
+            id,
+            controller_send,
+            controller_recv,
+            packet_recv,
+            packet_send,
+            pdr: 0.0,
+            behavior: Box::new(DefaultBehavior),
+            seed: None,
+            flood_cache: FloodCachePolicy::default(),
+            crash_deadline: None,
+            controller_failure: ControllerFailurePolicy::default(),
+            flypath_recv: crossbeam_channel::never(),
+            flypath_send: None,
+            message_send: None,
+            #[cfg(feature = "modes")]
+            catalog: Catalog::Embedded,
+            #[cfg(feature = "modes")]
+            catalog_watch: None,
+            #[cfg(feature = "modes")]
+            language: None,
+        }
+    }
+
+    /// Packet drop rate, must be between `0.0` and `1.0`
+    pub fn pdr(mut self, pdr: f32) -> Self {
+        self.pdr = pdr;
+        self
+    }
+
+    /// Use one of the built-in behaviors, see `FlyPathModes::behavior`
+    pub fn mode(mut self, mode: FlyPathModes) -> Self {
+        self.behavior = mode.behavior();
+        self
+    }
+
+    /// Use a custom `DroneBehavior` instead of one of the `FlyPathModes`
+    pub fn behavior(mut self, behavior: impl DroneBehavior + 'static) -> Self {
+        self.behavior = Box::new(behavior);
+        self
+    }
+
+    /// Shortcut for `mode(FlyPathModes::Spicy(theme))`
+    #[cfg(feature = "modes")]
+    pub fn theme(mut self, theme: FlyPathThemes) -> Self {
+        self.behavior = FlyPathModes::Spicy(theme).behavior();
+        self
+    }
+
+    /// Use the caller-supplied `Messages` instead of the embedded catalog
+    #[cfg(feature = "modes")]
+    pub fn messages(mut self, messages: Messages) -> Self {
+        self.catalog = Catalog::Messages(messages);
+        self
+    }
+
+    /// Load the messages from the catalog file (JSON or TOML, see `Messages::load_from_file`) at `catalog_path`
+    /// instead of the embedded catalog
+    #[cfg(feature = "modes")]
+    pub fn catalog_path(mut self, catalog_path: impl Into<String>) -> Self {
+        self.catalog = Catalog::Path(catalog_path.into());
+        self
+    }
+
+    /// Check the modification time and length of the `catalog_path` file every `interval` and reload the catalog
+    /// when it changes. If the new catalog is not valid the drone keeps the previous one.
+    #[cfg(feature = "modes")]
+    pub fn watch_catalog(mut self, interval: Duration) -> Self {
+        self.catalog_watch = Some(interval);
+        self
+    }
+
+    /// Language of the messages, e.g. `"it"`: a list without a variant for it falls back to its parent
+    /// language and then to the fallback language of the catalog, see `Messages::get_messages_for_language`
+    #[cfg(feature = "modes")]
+    pub fn language(mut self, language: &str) -> Self {
+        self.language = Some(language.to_string());
+        self
+    }
+
+    /// Seed of the random source, see `FlyPath::with_seed`
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// How the drone remembers the flood requests already seen
+    pub fn flood_cache(mut self, policy: FloodCachePolicy) -> Self {
+        self.flood_cache = policy;
+        self
+    }
+
+    /// Maximum time the drone keeps serving packets after a `Crash`, even if some neighbors are
+    /// still connected
+    pub fn crash_deadline(mut self, deadline: Duration) -> Self {
+        self.crash_deadline = Some(deadline);
+        self
+    }
+
+    /// What the drone does when a `DroneEvent` can not be sent to the controller
+    pub fn controller_failure(mut self, policy: ControllerFailurePolicy) -> Self {
+        self.controller_failure = policy;
+        self
+    }
+
+    /// Channel of the FlyPath-only commands, none by default
+    pub fn commands(mut self, flypath_recv: Receiver<FlyPathCommand>) -> Self {
+        self.flypath_recv = flypath_recv;
+        self
+    }
+
+    /// Channel of the replies to the `FlyPathCommand`s, none by default
+    pub fn replies(mut self, flypath_send: Sender<FlyPathReply>) -> Self {
+        self.flypath_send = Some(flypath_send);
+        self
+    }
+
+    /// Send the FlyPath messages (themed messages and acknowledgements) on `message_send` instead of
+    /// smuggling them in `DroneEvent::PacketSent`, so the controller only receives protocol events
+    pub fn messages_channel(mut self, message_send: Sender<FlyPathMessage>) -> Self {
+        self.message_send = Some(message_send);
+        self
+    }
+
+    /// Validate the configuration and create the drone.
+    ///
+    /// # Returns
+    /// - `Err(FlyPathError::InvalidConfig)` if:
+    ///     - the pdr is not between `0.0` and `1.0`
+    ///     - the drone is inside its own neighbor map
+    ///     - the flood cache has capacity or time window equal to zero
+    ///     - the controller failure policy buffers zero events
+    ///     - the catalog is watched with an interval equal to zero or is not loaded from a path
+    /// - `Err(FlyPathError::Io)`, `Err(FlyPathError::Parse)` or `Err(FlyPathError::InvalidCatalog)` if the
+    ///   catalog cannot be read, parsed or is not valid
+    pub fn build(self) -> Result<FlyPath, FlyPathError> {
+        if !(0.0..=1.0).contains(&self.pdr) {
+            return Err(FlyPathError::InvalidConfig(format!(
+                "Invalid packet drop rate: {} is not between 0 and 1",
+                self.pdr
+            )));
+        }
+        if self.packet_send.contains_key(&self.id) {
+            return Err(FlyPathError::InvalidConfig(format!(
+                "Drone {} can not be its own neighbor",
+                self.id
+            )));
+        }
+
+        match self.flood_cache {
+            FloodCachePolicy::Lru { capacity: 0 } => {
+                return Err(FlyPathError::InvalidConfig(
+                    "Invalid flood cache: capacity must be greater than 0".to_string(),
+                ))
+            }
+            FloodCachePolicy::TimeWindow(window) if window.is_zero() => {
+                return Err(FlyPathError::InvalidConfig(
+                    "Invalid flood cache: time window must be greater than 0".to_string(),
+                ))
+            }
+            _ => {}
+        }
+
+        if self.controller_failure == (ControllerFailurePolicy::Buffer { limit: 0 }) {
+            return Err(FlyPathError::InvalidConfig(
+                "Invalid controller failure policy: buffer limit must be greater than 0"
+                    .to_string(),
+            ));
+        }
+
+        #[cfg(feature = "modes")]
+        let catalog_tick = match (self.catalog_watch, &self.catalog) {
+            (None, _) => crossbeam_channel::never(),
+            (Some(interval), _) if interval.is_zero() => {
+                return Err(FlyPathError::InvalidConfig(
+                    "Invalid catalog watch: interval must be greater than 0".to_string(),
+                ))
+            }
+            (Some(interval), Catalog::Path(_)) => crossbeam_channel::tick(interval),
+            (Some(_), _) => {
+                return Err(FlyPathError::InvalidConfig(
+                    "Invalid catalog watch: the catalog must be loaded from a path".to_string(),
+                ))
+            }
+        };
+        #[cfg(not(feature = "modes"))]
+        let catalog_tick = crossbeam_channel::never();
+
+        #[cfg(feature = "modes")]
+        let catalog_stamp = match &self.catalog {
+            Catalog::Path(path) => Messages::stamp(path),
+            _ => None,
+        };
+        #[cfg(feature = "modes")]
+        let (messages, catalog_path) = match self.catalog {
+            Catalog::Embedded => (Messages::default().into_valid()?, None),
+            Catalog::Messages(messages) => (messages.into_valid()?, None),
+            Catalog::Path(path) => (Messages::load_from_file(&path)?.into_valid()?, Some(path)),
+        };
+
+        Ok(FlyPath {
+            id: self.id,
+            controller_send: self.controller_send,
+            controller_recv: self.controller_recv,
+            packet_recv: self.packet_recv,
+            packet_send: self.packet_send,
+            pdr: self.pdr,
+            precFloodId: FloodCache::new(self.flood_cache),
+            rng: match self.seed {
+                Some(seed) => StdRng::seed_from_u64(seed),
+                None => StdRng::from_entropy(),
+            },
+            stats: StatsHandle::default(),
+            crash_deadline: self.crash_deadline,
+            controller_failure: self.controller_failure,
+            pending_events: VecDeque::new(),
+            undelivered_events: 0,
+            controller_failed: false,
+            behavior: self.behavior,
+            flypath_recv: self.flypath_recv,
+            flypath_send: self.flypath_send,
+            link_pdr: HashMap::new(),
+            maintenance: false,
+            verbosity: MessageVerbosity::default(),
+            message_seq: 0,
+            dropped_nack: None,
+            message_send: self.message_send,
+            catalog_tick,
+            #[cfg(feature = "modes")]
+            messages: Arc::new(messages),
+            #[cfg(feature = "modes")]
+            selector: match &self.language {
+                Some(language) => MessageSelector::with_language(language),
+                None => MessageSelector::new(),
+            },
+            #[cfg(feature = "modes")]
+            catalog_path,
+            #[cfg(feature = "modes")]
+            catalog_stamp,
+        })
+    }
+}
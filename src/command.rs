@@ -0,0 +1,122 @@
+use crate::error::FlyPathError;
+use crate::flypath::FlyPathModes;
+use crate::stats::FlyPathStats;
+use std::collections::HashMap;
+use wg_2024::network::NodeId;
+
+#[cfg(feature = "modes")]
+use crate::flypath::FlyPathThemes;
+
+/// FlyPath-only commands, received on the channel given to `FlyPathBuilder::commands`.
+///
+/// The drone selects on this channel with lower priority than `DroneCommand`. Every command that
+/// changes the drone is acknowledged with a FlyPath message (see `extract_flypath_message`), and every
+/// command gets a `FlyPathReply` if the drone has a reply channel (see `FlyPathBuilder::replies`).
+#[derive(Debug, Clone)]
+pub enum FlyPathCommand {
+    /// Switch to the behavior of `mode`
+    SetMode(FlyPathModes),
+    /// Shortcut for `SetMode(FlyPathModes::Spicy(theme))`, fails if a `FlyPathThemes::Custom` theme is not in the catalog
+    #[cfg(feature = "modes")]
+    SetTheme(FlyPathThemes),
+    /// Load again the catalog from the path given to `FlyPathBuilder::catalog_path`.
+    /// If the new catalog is not valid the drone keeps the previous one.
+    #[cfg(feature = "modes")]
+    ReloadCatalog,
+    /// Reply with `FlyPathReply::Stats`
+    QueryStats,
+    /// Drop rate of the fragments sent to `neighbor`, `None` to use the pdr of the drone again
+    SetLinkPdr { neighbor: NodeId, pdr: Option<f32> },
+    /// Stop forwarding fragments, they are nacked with `ErrorInRouting` as in the crashing state.
+    /// Acks, nacks, flood requests, flood responses and commands are still handled, so the drone stays
+    /// visible to the topology discovery.
+    EnterMaintenance,
+    /// Forward fragments again
+    LeaveMaintenance,
+    /// Reply with `FlyPathReply::State`
+    DumpState,
+    /// Which themed messages the drone sends
+    SetVerbosity(MessageVerbosity),
+}
+
+impl FlyPathCommand {
+    // Trigger of the acknowledgement
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            FlyPathCommand::SetMode(_) => "SetMode",
+            #[cfg(feature = "modes")]
+            FlyPathCommand::SetTheme(_) => "SetTheme",
+            #[cfg(feature = "modes")]
+            FlyPathCommand::ReloadCatalog => "ReloadCatalog",
+            FlyPathCommand::QueryStats => "QueryStats",
+            FlyPathCommand::SetLinkPdr { .. } => "SetLinkPdr",
+            FlyPathCommand::EnterMaintenance => "EnterMaintenance",
+            FlyPathCommand::LeaveMaintenance => "LeaveMaintenance",
+            FlyPathCommand::DumpState => "DumpState",
+            FlyPathCommand::SetVerbosity(_) => "SetVerbosity",
+        }
+    }
+
+    // Used in the acknowledgement of a failed command
+    pub(crate) fn description(&self) -> &'static str {
+        match self {
+            FlyPathCommand::SetMode(_) => "Mode switch",
+            #[cfg(feature = "modes")]
+            FlyPathCommand::SetTheme(_) => "Theme switch",
+            #[cfg(feature = "modes")]
+            FlyPathCommand::ReloadCatalog => "Catalog reload",
+            FlyPathCommand::QueryStats => "Stats query",
+            FlyPathCommand::SetLinkPdr { .. } => "Link pdr change",
+            FlyPathCommand::EnterMaintenance => "Maintenance start",
+            FlyPathCommand::LeaveMaintenance => "Maintenance end",
+            FlyPathCommand::DumpState => "State dump",
+            FlyPathCommand::SetVerbosity(_) => "Verbosity change",
+        }
+    }
+}
+
+/// Which themed messages the drone sends to the controller
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MessageVerbosity {
+    /// No themed message
+    Off,
+    /// Only the messages about `DroneCommand`s, including the refused ones of BrainRot
+    Commands,
+    /// Every themed message
+    #[default]
+    All,
+}
+
+/// Answer to a `FlyPathCommand`, sent on the channel given to `FlyPathBuilder::replies`
+#[derive(Debug)]
+pub enum FlyPathReply {
+    /// The command has been applied
+    Done,
+    /// The command could not be applied, the drone did not change
+    Failed(FlyPathError),
+    /// Answer to `FlyPathCommand::QueryStats`
+    Stats(FlyPathStats),
+    /// Answer to `FlyPathCommand::DumpState`
+    State(FlyPathState),
+}
+
+/// Snapshot of the configuration of a drone, answer to `FlyPathCommand::DumpState`
+///
+/// # Fields
+/// - `neighbors`: ids of the neighbors, sorted
+/// - `behavior`: `Debug` of the `DroneBehavior`
+/// - `link_pdr`: pdr of the links set with `FlyPathCommand::SetLinkPdr`
+/// - `flood_cache_len`: flood requests remembered by the drone
+/// - `pending_events`: events buffered by `ControllerFailurePolicy::Buffer`
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlyPathState {
+    pub id: NodeId,
+    pub pdr: f32,
+    pub link_pdr: HashMap<NodeId, f32>,
+    pub neighbors: Vec<NodeId>,
+    pub behavior: String,
+    pub maintenance: bool,
+    pub verbosity: MessageVerbosity,
+    pub flood_cache_len: usize,
+    pub pending_events: usize,
+}
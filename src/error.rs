@@ -0,0 +1,58 @@
+use std::{fmt, io};
+
+#[cfg(feature = "modes")]
+use crate::messages::CatalogReport;
+
+/// Errors returned by the fallible FlyPath APIs
+#[derive(Debug)]
+pub enum FlyPathError {
+    /// A `DroneEvent` could not be sent because the controller dropped the event receiver
+    ControllerUnreachable,
+    /// The catalog file at `path` could not be read
+    Io { path: String, source: io::Error },
+    /// The catalog, JSON or TOML, is not well formed, with the message of the parser
+    Parse(String),
+    /// The catalog has been parsed but `Messages::validate` found at least one error
+    #[cfg(feature = "modes")]
+    InvalidCatalog(CatalogReport),
+    /// The message needs `len` bytes but a `Fragment` contains at most `max` bytes
+    MessageTooLong { len: usize, max: usize },
+    /// The name does not match any `FlyPathThemes`
+    UnknownTheme(String),
+    /// The builder configuration is not valid, e.g. a pdr out of range
+    InvalidConfig(String),
+}
+
+impl fmt::Display for FlyPathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FlyPathError::ControllerUnreachable => write!(f, "Controller is unreachable"),
+            FlyPathError::Io { path, source } => {
+                write!(f, "Failed to read the messages file {}: {}", path, source)
+            }
+            FlyPathError::Parse(message) => {
+                write!(f, "Failed to parse the message catalog: {}", message)
+            }
+            #[cfg(feature = "modes")]
+            FlyPathError::InvalidCatalog(report) => {
+                write!(f, "Invalid message catalog: {}", report)
+            }
+            FlyPathError::MessageTooLong { len, max } => write!(
+                f,
+                "Failed to generate a message: Too Long ({} bytes, at most {})",
+                len, max
+            ),
+            FlyPathError::UnknownTheme(name) => write!(f, "Unknown theme: {}", name),
+            FlyPathError::InvalidConfig(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for FlyPathError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FlyPathError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,244 @@
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+use wg_2024::network::NodeId;
+
+/// A flood request is identified by its `(flood_id, initiator_id)`
+type FloodKey = (u64, NodeId);
+
+/// How the drone remembers the flood requests already seen
+#[derive(Debug, Clone, PartialEq)]
+pub enum FloodCachePolicy {
+    /// Remember only the highest `flood_id` of every initiator: a request with a lower or equal
+    /// `flood_id` is a duplicate. Needs initiators that use increasing flood ids.
+    PerInitiator,
+    /// Remember the last `capacity` requests, evicting the least recently seen
+    Lru { capacity: usize },
+    /// Remember every request seen in the last `window`
+    TimeWindow(Duration),
+}
+
+impl Default for FloodCachePolicy {
+    fn default() -> Self {
+        FloodCachePolicy::Lru { capacity: 1024 }
+    }
+}
+
+/// Bounded set of the flood requests already seen by the drone.
+///
+/// The memory used depends on the `FloodCachePolicy`: at most one entry for every initiator, at most
+/// `capacity` entries, or the requests seen inside the time window.
+#[derive(Debug, Clone)]
+pub struct FloodCache {
+    policy: FloodCachePolicy,
+    /// highest flood id of every initiator, used by `PerInitiator`
+    highest: HashMap<NodeId, u64>,
+    /// last time a request has been seen: a tick for `Lru`, unused for `TimeWindow`
+    ticks: HashMap<FloodKey, u64>,
+    /// when a request has been inserted, used by `TimeWindow`
+    inserted: HashMap<FloodKey, Instant>,
+    /// requests from the oldest to the newest, may contain stale entries for `Lru`
+    order: VecDeque<(FloodKey, u64, Instant)>,
+    tick: u64,
+}
+
+impl FloodCache {
+    pub fn new(policy: FloodCachePolicy) -> Self {
+        Self {
+            policy,
+            highest: HashMap::new(),
+            ticks: HashMap::new(),
+            inserted: HashMap::new(),
+            order: VecDeque::new(),
+            tick: 0,
+        }
+    }
+
+    pub fn policy(&self) -> &FloodCachePolicy {
+        &self.policy
+    }
+
+    /// `true` if the `(flood_id, initiator_id)` request has already been seen.
+    /// With `Lru` a request found in the cache becomes the most recently seen.
+    pub fn contains(&mut self, key: &(u64, NodeId)) -> bool {
+        self.contains_at(key, Instant::now())
+    }
+
+    /// Remember the `(flood_id, initiator_id)` request, evicting old requests if needed
+    pub fn insert(&mut self, key: (u64, NodeId)) {
+        self.insert_at(key, Instant::now())
+    }
+
+    /// Number of requests remembered, for `PerInitiator` the number of initiators
+    pub fn len(&self) -> usize {
+        match self.policy {
+            FloodCachePolicy::PerInitiator => self.highest.len(),
+            FloodCachePolicy::Lru { .. } => self.ticks.len(),
+            FloodCachePolicy::TimeWindow(_) => self.inserted.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn contains_at(&mut self, key: &FloodKey, now: Instant) -> bool {
+        let (flood_id, initiator_id) = *key;
+        match self.policy {
+            FloodCachePolicy::PerInitiator => self
+                .highest
+                .get(&initiator_id)
+                .is_some_and(|highest| flood_id <= *highest),
+            FloodCachePolicy::Lru { .. } => {
+                if self.ticks.contains_key(key) {
+                    self.touch(*key, now);
+                    true
+                } else {
+                    false
+                }
+            }
+            FloodCachePolicy::TimeWindow(window) => {
+                self.expire(window, now);
+                self.inserted.contains_key(key)
+            }
+        }
+    }
+
+    fn insert_at(&mut self, key: FloodKey, now: Instant) {
+        let (flood_id, initiator_id) = key;
+        match self.policy {
+            FloodCachePolicy::PerInitiator => {
+                let highest = self.highest.entry(initiator_id).or_insert(flood_id);
+                *highest = (*highest).max(flood_id);
+            }
+            FloodCachePolicy::Lru { capacity } => {
+                self.touch(key, now);
+                while self.ticks.len() > capacity {
+                    self.evict_oldest();
+                }
+            }
+            FloodCachePolicy::TimeWindow(window) => {
+                self.expire(window, now);
+                if let Entry::Vacant(entry) = self.inserted.entry(key) {
+                    entry.insert(now);
+                    self.order.push_back((key, 0, now));
+                }
+            }
+        }
+    }
+
+    // Mark `key` as the most recently seen request
+    fn touch(&mut self, key: FloodKey, now: Instant) {
+        self.tick += 1;
+        self.ticks.insert(key, self.tick);
+        self.order.push_back((key, self.tick, now));
+
+        // drop the stale entries left by the previous touches so that `order` stays bounded
+        if self.order.len() > 2 * self.ticks.len().max(1) {
+            let ticks = &self.ticks;
+            self.order
+                .retain(|(key, tick, _)| ticks.get(key) == Some(tick));
+        }
+    }
+
+    fn evict_oldest(&mut self) {
+        while let Some((key, tick, _)) = self.order.pop_front() {
+            if self.ticks.get(&key) == Some(&tick) {
+                self.ticks.remove(&key);
+                return;
+            }
+        }
+    }
+
+    fn expire(&mut self, window: Duration, now: Instant) {
+        while let Some((key, _, inserted)) = self.order.front() {
+            if now.duration_since(*inserted) < window {
+                break;
+            }
+            self.inserted.remove(key);
+            self.order.pop_front();
+        }
+    }
+}
+
+impl Default for FloodCache {
+    fn default() -> Self {
+        Self::new(FloodCachePolicy::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_per_initiator() {
+        let mut cache = FloodCache::new(FloodCachePolicy::PerInitiator);
+        assert!(!cache.contains(&(5, 1)));
+        cache.insert((5, 1));
+
+        // duplicates and older requests of the same initiator
+        assert!(cache.contains(&(5, 1)));
+        assert!(cache.contains(&(3, 1)));
+        // newer request and other initiators
+        assert!(!cache.contains(&(6, 1)));
+        assert!(!cache.contains(&(5, 2)));
+
+        for flood_id in 0..1000 {
+            cache.insert((flood_id, 2));
+        }
+        assert!(cache.contains(&(999, 2)));
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn test_lru() {
+        let mut cache = FloodCache::new(FloodCachePolicy::Lru { capacity: 3 });
+        cache.insert((1, 1));
+        cache.insert((2, 1));
+        cache.insert((3, 1));
+        assert!(cache.contains(&(1, 1)));
+        assert!(cache.contains(&(2, 1)));
+        assert!(cache.contains(&(3, 1)));
+
+        // (1, 1) has been seen again, so (2, 1) is the least recently seen
+        assert!(cache.contains(&(1, 1)));
+        cache.insert((4, 1));
+        assert!(!cache.contains(&(2, 1)));
+        assert!(cache.contains(&(1, 1)));
+        assert!(cache.contains(&(3, 1)));
+        assert!(cache.contains(&(4, 1)));
+        assert_eq!(cache.len(), 3);
+
+        // the cache never grows over the capacity
+        for flood_id in 0..1000 {
+            cache.insert((flood_id, 2));
+            assert!(cache.contains(&(flood_id, 2)));
+        }
+        assert_eq!(cache.len(), 3);
+        for _ in 0..1000 {
+            assert!(cache.contains(&(999, 2)));
+        }
+        assert!(cache.order.len() <= 2 * 3 + 1);
+    }
+
+    #[test]
+    fn test_time_window() {
+        let window = Duration::from_secs(10);
+        let mut cache = FloodCache::new(FloodCachePolicy::TimeWindow(window));
+        let start = Instant::now();
+
+        cache.insert_at((1, 1), start);
+        cache.insert_at((2, 1), start + Duration::from_secs(5));
+        assert!(cache.contains_at(&(1, 1), start + Duration::from_secs(9)));
+        assert!(cache.contains_at(&(2, 1), start + Duration::from_secs(9)));
+
+        // (1, 1) is out of the window, (2, 1) is still inside
+        assert!(!cache.contains_at(&(1, 1), start + Duration::from_secs(10)));
+        assert!(cache.contains_at(&(2, 1), start + Duration::from_secs(14)));
+        assert_eq!(cache.len(), 1);
+
+        assert!(!cache.contains_at(&(2, 1), start + Duration::from_secs(15)));
+        assert!(cache.is_empty());
+    }
+}
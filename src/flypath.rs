@@ -1,19 +1,41 @@
-use crossbeam_channel::{select_biased, Receiver, Sender};
-use rand::Rng;
-use std::collections::{HashMap, HashSet};
+use crate::behavior::{DefaultBehavior, DroneBehavior, DroneContext};
+use crate::builder::FlyPathBuilder;
+use crate::command::{FlyPathCommand, FlyPathReply, FlyPathState, MessageVerbosity};
+use crate::error::FlyPathError;
+use crate::flood_cache::FloodCache;
+use crate::flypath_message::{
+    truncate, FlyPathMessage, FlyPathMessageKind, FLYPATH_MESSAGE_MAX_LEN,
+};
+use crate::stats::StatsHandle;
+use crossbeam_channel::{select_biased, Receiver, Sender, TrySendError};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
 use wg_2024::controller::{DroneCommand, DroneEvent};
 use wg_2024::drone::Drone;
 use wg_2024::network::{NodeId, SourceRoutingHeader};
 use wg_2024::packet::{Nack, NackType, NodeType, Packet, PacketType};
 
+#[cfg(feature = "modes")]
+use crate::behavior::{BrainRotBehavior, SpicyBehavior};
 #[cfg(feature = "modes")]
 use crate::messages::Messages;
 #[cfg(feature = "modes")]
-use rand::seq::SliceRandom;
+use crate::selection::MessageSelector;
 #[cfg(feature = "modes")]
 use std::fmt;
 #[cfg(feature = "modes")]
-use wg_2024::packet::{FloodRequest, Fragment};
+use std::str::FromStr;
+#[cfg(feature = "modes")]
+use std::sync::Arc;
+#[cfg(feature = "modes")]
+use std::time::SystemTime;
+// How often the events buffered by `ControllerFailurePolicy::Buffer` are sent again
+const PENDING_EVENTS_RETRY: Duration = Duration::from_millis(10);
+// How long a stopping drone waits for the controller to receive the buffered events
+const PENDING_EVENTS_EXIT_TIMEOUT: Duration = Duration::from_secs(1);
+
 /// Enum representing the enabled fly path modes.
 #[derive(Debug, Clone)]
 pub enum FlyPathModes {
@@ -29,8 +51,23 @@ pub enum FlyPathModes {
     BrainRot,
 }
 
+impl FlyPathModes {
+    /// The `DroneBehavior` that implements the mode
+    pub fn behavior(&self) -> Box<dyn DroneBehavior> {
+        match self {
+            FlyPathModes::Default => Box::new(DefaultBehavior),
+            #[cfg(feature = "modes")]
+            FlyPathModes::Spicy(theme) => Box::new(SpicyBehavior::new(theme.clone())),
+            #[cfg(feature = "modes")]
+            FlyPathModes::BrainRot => Box::new(BrainRotBehavior),
+        }
+    }
+}
+
 /// Represents various themes for fly paths, available when the `modes` feature is enabled.
-#[derive(Debug, Clone)]
+///
+/// `Custom` selects any other theme of the catalog by its name, see `Messages::themes`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 #[cfg(feature = "modes")]
 pub enum FlyPathThemes {
     Batman,
@@ -41,18 +78,35 @@ pub enum FlyPathThemes {
     DarkSouls,
     Bloodborne,
     Pingu,
+    /// A theme defined only in the catalog, e.g. `Custom("Star Wars".to_string())`
+    Custom(String),
+}
+
+#[cfg(feature = "modes")]
+impl FlyPathThemes {
+    /// Every built-in theme, used to check that a catalog covers all of them
+    pub const ALL: [FlyPathThemes; 7] = [
+        FlyPathThemes::Batman,
+        FlyPathThemes::Rocket,
+        FlyPathThemes::Quackable,
+        FlyPathThemes::HarryPotter,
+        FlyPathThemes::DarkSouls,
+        FlyPathThemes::Bloodborne,
+        FlyPathThemes::Pingu,
+    ];
 }
 
 #[cfg(feature = "modes")]
 impl fmt::Display for FlyPathThemes {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let theme_str = match self {
+            FlyPathThemes::Custom(name) => name,
             FlyPathThemes::Batman => "Batman",
             FlyPathThemes::Rocket => "Rocket",
             FlyPathThemes::Quackable => "Quackable",
-            FlyPathThemes::HarryPotter => "HarryPotter",
+            FlyPathThemes::HarryPotter => "Harry Potter",
             // FlyPathThemes::GerryScotty => "GerryScotty",
-            FlyPathThemes::DarkSouls => "DarkSoul",
+            FlyPathThemes::DarkSouls => "Dark Souls",
             FlyPathThemes::Bloodborne => "Bloodborne",
             FlyPathThemes::Pingu => "Pingu",
         };
@@ -60,8 +114,57 @@ impl fmt::Display for FlyPathThemes {
     }
 }
 
+/// Parse a built-in theme from its `Display` name (e.g. `"Dark Souls"`) or its variant name (e.g. `"DarkSouls"`),
+/// use `Messages::theme` to accept the custom themes of a catalog as well
 #[cfg(feature = "modes")]
-const FILE_PATH: &str = "resources/messages.json";
+impl FromStr for FlyPathThemes {
+    type Err = FlyPathError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        FlyPathThemes::ALL
+            .iter()
+            .find(|theme| theme.to_string() == name || format!("{:?}", theme) == name)
+            .cloned()
+            .ok_or_else(|| FlyPathError::UnknownTheme(name.to_string()))
+    }
+}
+
+/// Why a drone stopped running, returned by `FlyPath::try_run`
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExitReason {
+    /// The drone received `DroneCommand::Crash` and the crashing state is over
+    Crashed,
+    /// The controller dropped the sender of the commands
+    ControllerDisconnected,
+    /// All the senders of the packet channel have been dropped
+    PacketChannelDisconnected,
+    /// The controller could not receive the events and `policy` required the drone to stop.
+    /// `undelivered` is the number of events that never reached the controller.
+    ControllerUnreachable {
+        policy: ControllerFailurePolicy,
+        undelivered: usize,
+    },
+}
+
+/// What the drone does when a `DroneEvent` can not be sent to the controller
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum ControllerFailurePolicy {
+    /// `run` panics and `try_run` returns `FlyPathError::ControllerUnreachable`, the default.
+    /// The drone first finishes handling the current packet or command, so the panic happens in `run`
+    /// and not where the event was sent.
+    #[default]
+    Panic,
+    /// Stop the drone, `try_run` returns `ExitReason::ControllerUnreachable`
+    Stop,
+    /// For a bounded controller channel: while the channel is full keep up to `limit` events and send
+    /// them again, in order, as soon as the channel has room, instead of blocking the drone. When more
+    /// than `limit` events are waiting or the controller disconnects, the drone stops as with `Stop`.
+    /// A stopping drone waits up to one second for the controller to receive the buffered events, the
+    /// ones left are reported as undelivered with `ExitReason::ControllerUnreachable`
+    Buffer { limit: usize },
+    /// Print the event on stderr and keep running
+    LogAndDrop,
+}
 
 /// Implementation of Drone
 #[derive(Debug, Clone)]
@@ -72,83 +175,106 @@ pub struct FlyPath {
     pub packet_recv: Receiver<Packet>,
     pub packet_send: HashMap<NodeId, Sender<Packet>>,
     pub pdr: f32,
-    /// set used to memorize old floor requests
-    pub precFloodId: HashSet<(u64, u8)>,
-
-    /// drone's mode
-    pub mode: FlyPathModes,
-    /// struct that contains loaded messages from FILE_PATH
+    /// bounded cache used to memorize old flood requests
+    pub precFloodId: FloodCache,
+    /// source of every random decision: packet drops, BrainRot choices and message selection
+    pub rng: StdRng,
+    /// traffic statistics, shared with the handles returned by `stats`
+    pub stats: StatsHandle,
+    /// how long the drone keeps serving after a `Crash`, `None` waits until all the neighbors disconnect
+    pub crash_deadline: Option<Duration>,
+    /// what the drone does when a `DroneEvent` can not be sent to the controller
+    pub controller_failure: ControllerFailurePolicy,
+    /// events waiting to be sent again with `ControllerFailurePolicy::Buffer`
+    pub(crate) pending_events: VecDeque<DroneEvent>,
+    /// events that will never reach the controller
+    pub(crate) undelivered_events: usize,
+    /// set when the controller failure policy requires the drone to stop
+    pub(crate) controller_failed: bool,
+
+    /// how the drone reacts to commands and packets, see `FlyPathModes` for the built-in behaviors
+    pub behavior: Box<dyn DroneBehavior>,
+    /// FlyPath-only commands, `crossbeam_channel::never()` if the drone has no such channel
+    pub flypath_recv: Receiver<FlyPathCommand>,
+    /// replies to the FlyPath-only commands
+    pub flypath_send: Option<Sender<FlyPathReply>>,
+    /// pdr of the fragments sent to a neighbor, used instead of `pdr`
+    pub link_pdr: HashMap<NodeId, f32>,
+    /// in maintenance the fragments are nacked instead of forwarded, flood requests are still handled
+    pub maintenance: bool,
+    /// which themed messages the drone sends
+    pub verbosity: MessageVerbosity,
+    /// sequence number of the next FlyPath message
+    pub(crate) message_seq: u32,
+    /// the nack being sent while `DroneBehavior::on_packet_dropped` runs
+    pub(crate) dropped_nack: Option<NackType>,
+    /// channel of the FlyPath messages, `None` sends them to the controller as `PacketSent` events
+    pub message_send: Option<Sender<FlyPathMessage>>,
+    /// ticks of the catalog watch, `crossbeam_channel::never()` if the catalog is not watched
+    pub(crate) catalog_tick: Receiver<Instant>,
+    /// struct that contains the loaded messages, by default the catalog embedded in the crate.
+    /// A reload replaces it only once the new catalog has been parsed and validated.
     #[cfg(feature = "modes")]
-    pub messages: Messages,
+    pub messages: Arc<Messages>,
+    /// position of the round-robin lists and messages left in the shuffle bags of the catalog
+    #[cfg(feature = "modes")]
+    pub(crate) selector: MessageSelector,
+    /// path of the catalog, used by `FlyPathCommand::ReloadCatalog` and by the catalog watch
+    #[cfg(feature = "modes")]
+    pub catalog_path: Option<String>,
+    /// modification time and length of the catalog when it was last loaded
+    #[cfg(feature = "modes")]
+    pub(crate) catalog_stamp: Option<(SystemTime, u64)>,
 }
 
 impl Drone for FlyPath {
+    /// Thin wrapper around `FlyPathBuilder`, use the builder directly to configure the other options.
+    ///
+    /// Unlike `FlyPathBuilder::build`, it never rejects the configuration of a controller:
+    /// - a pdr outside of `0.0..=1.0` is clamped to the nearest bound, `NaN` becomes `0.0`
+    /// - the drone is removed from its own neighbor map
+    ///
+    /// Use `FlyPathBuilder::build` to get an error for these configurations instead.
     fn new(
         id: NodeId,
         controller_send: Sender<DroneEvent>,
         controller_recv: Receiver<DroneCommand>,
         packet_recv: Receiver<Packet>,
-        packet_send: HashMap<NodeId, Sender<Packet>>,
+        mut packet_send: HashMap<NodeId, Sender<Packet>>,
         pdr: f32,
     ) -> Self {
-        Self {
-            mode: FlyPathModes::Default,
+        let pdr = if pdr.is_nan() {
+            0.0
+        } else {
+            pdr.clamp(0.0, 1.0)
+        };
+        packet_send.remove(&id);
+        FlyPathBuilder::new(
             id,
             controller_send,
             controller_recv,
             packet_recv,
             packet_send,
-            pdr,
-            precFloodId: HashSet::new(),
-            #[cfg(feature = "modes")]
-            messages: Messages::load_from_file(FILE_PATH).unwrap(),
-        }
+        )
+        .pdr(pdr)
+        .build()
+        .unwrap_or_else(|e| panic!("Invalid FlyPath configuration: {}", e))
     }
 
+    /// Run the drone until it crashes or one of its channels disconnects, see `try_run`.
+    ///
+    /// # Panics
+    /// If the controller is unreachable while the drone needs to send a `DroneEvent`.
     fn run(&mut self) {
-        loop {
-            select_biased! {
-                recv(self.controller_recv) -> cmd => {
-                    if let Ok(cmd) = cmd {
-                        match cmd {
-                            DroneCommand::Crash => {
-                                match &self.mode {
-                                    FlyPathModes::Default => {self.gentle_crash(); break;}
-                                    #[cfg(feature = "modes")]
-                                    FlyPathModes::Spicy(_) => {
-                                        self.command_flypath_message(&cmd);
-                                        self.gentle_crash();
-                                        break;
-                                    }
-                                    #[cfg(feature = "modes")]
-                                    FlyPathModes::BrainRot => {
-                                        let should_crash = 50 < rand::thread_rng().gen_range(0..100);
-                                        if should_crash {
-                                            self.brainRot_event_flypath_message("Crash");
-                                            break;
-                                        }else{
-                                            self.brainRot_event_flypath_message("NoCrash");
-                                            continue;
-                                        }
-                                    }
-                                };
-                            },
-                            _ => self.command_handler(cmd)
-                        };
-                    }
-                },
-                recv(self.packet_recv) -> packet => {
-                    if let Ok(packet) = packet {
-                        self.packet_handler(packet);
-                    }
-                }
-            }
+        if let Err(e) = self.try_run() {
+            panic!("{}", e);
         }
     }
 }
 
 impl FlyPath {
     #[cfg(feature = "modes")]
+    #[deprecated(note = "use `FlyPathBuilder` with `mode`")]
     pub fn new_with_mode(
         mode: FlyPathModes,
         id: NodeId,
@@ -158,149 +284,435 @@ impl FlyPath {
         packet_send: HashMap<NodeId, Sender<Packet>>,
         pdr: f32,
     ) -> Self {
-        Self {
-            mode,
+        FlyPathBuilder::new(
             id,
             controller_send,
             controller_recv,
             packet_recv,
             packet_send,
-            pdr,
-            precFloodId: HashSet::new(),
-            messages: Messages::load_from_file(FILE_PATH).unwrap(),
-        }
+        )
+        .pdr(pdr)
+        .mode(mode)
+        .build()
+        .unwrap_or_else(|e| panic!("Invalid FlyPath configuration: {}", e))
+    }
+
+    /// Same as `new_with_mode` but uses the caller-supplied `Messages` instead of the embedded catalog.
+    ///
+    /// # Returns
+    /// - `Err(FlyPathError::InvalidCatalog)`: if `Messages::validate` finds at least one error in the catalog
+    #[cfg(feature = "modes")]
+    #[deprecated(note = "use `FlyPathBuilder` with `mode` and `messages`")]
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_with_messages(
+        mode: FlyPathModes,
+        id: NodeId,
+        controller_send: Sender<DroneEvent>,
+        controller_recv: Receiver<DroneCommand>,
+        packet_recv: Receiver<Packet>,
+        packet_send: HashMap<NodeId, Sender<Packet>>,
+        pdr: f32,
+        messages: Messages,
+    ) -> Result<Self, FlyPathError> {
+        FlyPathBuilder::new(
+            id,
+            controller_send,
+            controller_recv,
+            packet_recv,
+            packet_send,
+        )
+        .pdr(pdr)
+        .mode(mode)
+        .messages(messages)
+        .build()
     }
 
-    // Manage all the remaining packet until all the senders has been disconnected
-    fn gentle_crash(&mut self) {
+    /// Same as `new_with_mode` but loads the messages from the catalog file (JSON or TOML, see
+    /// `Messages::load_from_file`) at `catalog_path`.
+    ///
+    /// # Returns
+    /// - `Err(FlyPathError)`: if the catalog cannot be read, parsed or is not valid
+    #[cfg(feature = "modes")]
+    #[deprecated(note = "use `FlyPathBuilder` with `mode` and `catalog_path`")]
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_with_catalog(
+        mode: FlyPathModes,
+        id: NodeId,
+        controller_send: Sender<DroneEvent>,
+        controller_recv: Receiver<DroneCommand>,
+        packet_recv: Receiver<Packet>,
+        packet_send: HashMap<NodeId, Sender<Packet>>,
+        pdr: f32,
+        catalog_path: &str,
+    ) -> Result<Self, FlyPathError> {
+        FlyPathBuilder::new(
+            id,
+            controller_send,
+            controller_recv,
+            packet_recv,
+            packet_send,
+        )
+        .pdr(pdr)
+        .mode(mode)
+        .catalog_path(catalog_path)
+        .build()
+    }
+
+    /// Run the drone and report why it stopped.
+    ///
+    /// # Returns
+    /// - `Ok(ExitReason::Crashed)`: after a `Crash`, once the crashing state is over
+    /// - `Ok(ExitReason::ControllerDisconnected)`: the controller dropped the command sender
+    /// - `Ok(ExitReason::PacketChannelDisconnected)`: all the senders of the packet channel have been dropped
+    /// - `Err(FlyPathError::ControllerUnreachable)`: a `DroneEvent` could not be sent to the controller
+    ///
+    /// # Example
+    /// ```ignore
+    /// let handle = thread::spawn(move || drone.try_run());
+    /// println!("drone stopped: {:?}", handle.join().unwrap());
+    /// ```
+    pub fn try_run(&mut self) -> Result<ExitReason, FlyPathError> {
         loop {
-            match self.packet_recv.try_recv() {
-                Ok(mut packet) => {
-                    match &packet.pack_type {
-                        PacketType::MsgFragment(_) => {
-                            self.send_nack(&packet, NackType::ErrorInRouting(self.id));
-                        }
-                        PacketType::FloodRequest(_) => {}
-                        // Ack, Nack, FloodResponse need to have be sended
-                        _ => self.send_packet(&mut packet),
+            // the buffered events are sent again even if the drone has nothing else to do
+            let retry = if self.pending_events.is_empty() {
+                crossbeam_channel::never()
+            } else {
+                crossbeam_channel::after(PENDING_EVENTS_RETRY)
+            };
+            select_biased! {
+                recv(self.controller_recv) -> cmd => {
+                    let Ok(cmd) = cmd else {
+                        return self.exit(ExitReason::ControllerDisconnected);
+                    };
+                    if !self.with_behavior(|behavior, ctx| behavior.on_command(ctx, &cmd)) {
+                        continue;
+                    }
+                    if let DroneCommand::Crash = cmd {
+                        self.crash();
+                        return self.exit(ExitReason::Crashed);
+                    }
+                    self.command_handler(cmd);
+                },
+                recv(self.flypath_recv) -> cmd => {
+                    match cmd {
+                        Ok(cmd) => self.flypath_command_handler(cmd),
+                        // the drone keeps running without FlyPath commands
+                        Err(_) => self.flypath_recv = crossbeam_channel::never(),
                     }
+                },
+                recv(self.catalog_tick) -> _ => self.watch_catalog(),
+                recv(self.packet_recv) -> packet => {
+                    let Ok(packet) = packet else {
+                        return self.exit(ExitReason::PacketChannelDisconnected);
+                    };
+                    // in maintenance the drone still takes part in the topology discovery
+                    if self.maintenance && !matches!(packet.pack_type, PacketType::FloodRequest(_)) {
+                        self.crashing_packet_handler(packet);
+                    } else {
+                        self.packet_handler(packet);
+                    }
+                },
+                recv(retry) -> _ => {
+                    self.flush_pending_events();
                 }
-                Err(crossbeam_channel::TryRecvError::Empty) => break,
-                Err(crossbeam_channel::TryRecvError::Disconnected) => break,
+            }
+            if let Some(exit) = self.controller_exit() {
+                return exit;
             }
         }
     }
 
-    // Given a CMD, get the message and send it
-    #[cfg(feature = "modes")]
-    fn command_flypath_message(&mut self, cmd: &DroneCommand) {
-        let flyPath_messages = self.messages.generate_droneEvent_to_controller(
-            &self.mode,
-            Messages::drone_command_to_string(cmd),
-            self.id,
-        );
-        if let Ok(Some(msg)) = flyPath_messages {
-            self.send_event(msg);
+    // Report the controller failure, if any, instead of `reason`
+    fn exit(&mut self, reason: ExitReason) -> Result<ExitReason, FlyPathError> {
+        if !self.controller_failed {
+            self.drain_pending_events();
         }
+        self.controller_exit().unwrap_or(Ok(reason))
     }
 
-    // Given a EVENT, get the message and send it
-    #[cfg(feature = "modes")]
-    fn event_flypath_message(&mut self, event: &DroneEvent) {
-        let flyPath_messages = self.messages.generate_droneEvent_to_controller(
-            &self.mode,
-            Messages::drone_event_to_string(event),
-            self.id,
-        );
-        if let Ok(Some(msg)) = flyPath_messages {
-            self.send_event(msg);
+    // How the drone stops if the controller failure policy requires it
+    fn controller_exit(&self) -> Option<Result<ExitReason, FlyPathError>> {
+        if !self.controller_failed {
+            return None;
+        }
+        match self.controller_failure {
+            ControllerFailurePolicy::Panic => Some(Err(FlyPathError::ControllerUnreachable)),
+            _ => Some(Ok(ExitReason::ControllerUnreachable {
+                policy: self.controller_failure.clone(),
+                undelivered: self.undelivered_events + self.pending_events.len(),
+            })),
         }
     }
 
-    #[cfg(feature = "modes")]
-    fn brainRot_event_flypath_message(&mut self, brainRot_event: &str) {
-        let flyPath_messages =
-            self.messages
-                .generate_droneEvent_to_controller(&self.mode, brainRot_event, self.id);
-        if let Ok(Some(msg)) = flyPath_messages {
-            self.send_event(msg);
+    /// Replace the random source with one seeded with `seed`.
+    /// Given the same seed and the same sequence of packets and commands, the drone makes exactly the
+    /// same drops, shuffles and message choices.
+    ///
+    /// # Example
+    /// ```ignore
+    /// let drone = FlyPath::new(id, controller_send, controller_recv, packet_recv, packet_send, pdr).with_seed(42);
+    /// ```
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+
+    /// Handle to the traffic statistics of the drone, it can be read from another thread while the drone runs.
+    ///
+    /// # Example
+    /// ```ignore
+    /// let stats = drone.stats();
+    /// thread::spawn(move || drone.run());
+    /// println!("{:?}", stats.snapshot().total);
+    /// ```
+    pub fn stats(&self) -> StatsHandle {
+        self.stats.clone()
+    }
+
+    // Crashing state: manage all the remaining packets until all the senders have been disconnected
+    // (or until the crash deadline expires). `RemoveSender` is still processed so the controller can
+    // disconnect the neighbors, every other command is ignored.
+    fn crash(&mut self) {
+        let deadline = match self.crash_deadline {
+            Some(timeout) => crossbeam_channel::after(timeout),
+            None => crossbeam_channel::never(),
+        };
+        let mut controller_connected = true;
+        loop {
+            // once the controller is gone only the packets and the deadline are left
+            let controller_recv = if controller_connected {
+                self.controller_recv.clone()
+            } else {
+                crossbeam_channel::never()
+            };
+            select_biased! {
+                recv(controller_recv) -> cmd => {
+                    match cmd {
+                        Ok(DroneCommand::RemoveSender(id)) => {
+                            self.packet_send.remove(&id);
+                        }
+                        Ok(_) => {}
+                        Err(_) => controller_connected = false,
+                    }
+                },
+                recv(self.packet_recv) -> packet => {
+                    match packet {
+                        Ok(packet) => self.crashing_packet_handler(packet),
+                        Err(_) => break,
+                    }
+                },
+                recv(deadline) -> _ => break,
+            }
+            if self.controller_failed {
+                break;
+            }
+        }
+    }
+
+    // While crashing (or in maintenance) fragments are nacked with `ErrorInRouting` and Ack, Nack,
+    // FloodResponse still need to be sent. Flood requests are lost while crashing, in maintenance
+    // they never get here
+    fn crashing_packet_handler(&mut self, mut packet: Packet) {
+        match &packet.pack_type {
+            PacketType::MsgFragment(_) => {
+                self.send_nack(&packet, NackType::ErrorInRouting(self.id));
+            }
+            PacketType::FloodRequest(_) => {}
+            _ => self.forward_packet(&mut packet),
         }
     }
 
-    // Handler AddSender, RemoveSender and Set packet drop rate
+    // Run a hook of the behavior, the behavior is taken out of the drone while the hook runs
+    fn with_behavior<R>(
+        &mut self,
+        hook: impl FnOnce(&mut dyn DroneBehavior, &mut DroneContext) -> R,
+    ) -> R {
+        let mut behavior = std::mem::replace(&mut self.behavior, Box::new(DefaultBehavior));
+        let result = hook(behavior.as_mut(), &mut DroneContext::new(self));
+        self.behavior = behavior;
+        result
+    }
+
+    // Handler AddSender, RemoveSender and Set packet drop rate, the behavior already accepted the command
     fn command_handler(&mut self, cmd: DroneCommand) {
-        match &cmd {
+        match cmd {
             DroneCommand::AddSender(id, sender) => {
-                match &self.mode {
-                    FlyPathModes::Default => {
-                        self.packet_send.insert(*id, sender.clone());
-                    }
-                    #[cfg(feature = "modes")]
-                    FlyPathModes::Spicy(_) => {
-                        self.command_flypath_message(&cmd);
-                        self.packet_send.insert(*id, sender.clone());
-                    }
-                    #[cfg(feature = "modes")]
-                    FlyPathModes::BrainRot => {
-                        // 50/50 add the sender
-                        let should_add_sender = 50 > rand::thread_rng().gen_range(0..100);
-                        if should_add_sender {
-                            self.brainRot_event_flypath_message("AddSender");
-                            self.packet_send.insert(*id, sender.clone());
-                        } else {
-                            self.brainRot_event_flypath_message("NoAddSender");
-                        }
-                    }
-                };
+                self.packet_send.insert(id, sender);
             }
             // TODO: check if really drop the channel testing that returns Err Disconnected
             DroneCommand::RemoveSender(id) => {
-                match &self.mode {
-                    FlyPathModes::Default => {
-                        self.packet_send.remove(id);
-                    }
-                    #[cfg(feature = "modes")]
-                    FlyPathModes::Spicy(_) => {
-                        self.command_flypath_message(&cmd);
-                        self.packet_send.remove(id);
-                    }
-                    #[cfg(feature = "modes")]
-                    FlyPathModes::BrainRot => {
-                        // 50/50 remove the sender
-                        let should_remove_sender = 50 > rand::thread_rng().gen_range(0..100);
-                        if should_remove_sender {
-                            self.brainRot_event_flypath_message("RemoveSender");
-                            self.packet_send.remove(id);
-                        } else {
-                            self.brainRot_event_flypath_message("NoRemoveSender");
-                        }
-                    }
-                };
+                self.packet_send.remove(&id);
             }
             DroneCommand::SetPacketDropRate(pdr) => {
-                match &self.mode {
-                    FlyPathModes::Default => {
-                        self.pdr = *pdr;
-                    }
-                    #[cfg(feature = "modes")]
-                    FlyPathModes::Spicy(_) => {
-                        self.command_flypath_message(&cmd);
-                        self.pdr = *pdr;
+                self.pdr = pdr;
+            }
+            DroneCommand::Crash => {}
+        }
+    }
+
+    // Answer or apply a FlyPath command, acknowledge the changes to the controller and reply
+    fn flypath_command_handler(&mut self, cmd: FlyPathCommand) {
+        let reply = match cmd {
+            FlyPathCommand::QueryStats => FlyPathReply::Stats(self.stats.snapshot()),
+            FlyPathCommand::DumpState => FlyPathReply::State(self.state()),
+            cmd => {
+                let name = cmd.name();
+                let description = cmd.description();
+                match self.apply_flypath_command(cmd) {
+                    Ok(ack) => {
+                        self.send_flypath_reply(name, &ack);
+                        FlyPathReply::Done
                     }
-                    #[cfg(feature = "modes")]
-                    FlyPathModes::BrainRot => {
-                        // 50/50 change pdr
-                        let should_change_pdr = 50 > rand::thread_rng().gen_range(0..100);
-                        if should_change_pdr {
-                            self.brainRot_event_flypath_message("SetPacketDropRate");
-                            self.pdr = *pdr;
-                        } else {
-                            self.brainRot_event_flypath_message("NoSetPacketDropRate");
-                        }
+                    Err(e) => {
+                        self.send_flypath_reply(name, &format!("{} failed: {}", description, e));
+                        FlyPathReply::Failed(e)
                     }
-                };
+                }
+            }
+        };
+        if let Some(flypath_send) = &self.flypath_send {
+            // nobody is waiting for the reply
+            let _ = flypath_send.send(reply);
+        }
+    }
+
+    // Apply a FlyPath command that changes the drone, returns the acknowledgement
+    fn apply_flypath_command(&mut self, cmd: FlyPathCommand) -> Result<String, FlyPathError> {
+        match cmd {
+            FlyPathCommand::SetMode(mode) => {
+                self.behavior = mode.behavior();
+                Ok(format!("Mode switched to {:?}", mode))
+            }
+            #[cfg(feature = "modes")]
+            FlyPathCommand::SetTheme(theme) => {
+                if matches!(theme, FlyPathThemes::Custom(_)) && !self.messages.has_theme(&theme) {
+                    return Err(FlyPathError::UnknownTheme(theme.to_string()));
+                }
+                self.behavior = FlyPathModes::Spicy(theme.clone()).behavior();
+                Ok(format!("Theme switched to {}", theme))
             }
-            _ => {}
+            #[cfg(feature = "modes")]
+            FlyPathCommand::ReloadCatalog => {
+                self.reload_catalog()?;
+                Ok("Catalog reloaded".to_string())
+            }
+            FlyPathCommand::SetLinkPdr {
+                neighbor,
+                pdr: Some(pdr),
+            } => {
+                if !(0.0..=1.0).contains(&pdr) {
+                    return Err(FlyPathError::InvalidConfig(format!(
+                        "Invalid packet drop rate: {} is not between 0 and 1",
+                        pdr
+                    )));
+                }
+                self.link_pdr.insert(neighbor, pdr);
+                Ok(format!("Pdr of the link to {} set to {}", neighbor, pdr))
+            }
+            FlyPathCommand::SetLinkPdr {
+                neighbor,
+                pdr: None,
+            } => {
+                self.link_pdr.remove(&neighbor);
+                Ok(format!("Pdr of the link to {} reset", neighbor))
+            }
+            FlyPathCommand::EnterMaintenance => {
+                self.maintenance = true;
+                Ok("Maintenance started".to_string())
+            }
+            FlyPathCommand::LeaveMaintenance => {
+                self.maintenance = false;
+                Ok("Maintenance ended".to_string())
+            }
+            FlyPathCommand::SetVerbosity(verbosity) => {
+                self.verbosity = verbosity;
+                Ok(format!("Verbosity set to {:?}", verbosity))
+            }
+            FlyPathCommand::QueryStats | FlyPathCommand::DumpState => Ok(String::new()),
+        }
+    }
+
+    fn state(&self) -> FlyPathState {
+        let mut neighbors: Vec<NodeId> = self.packet_send.keys().copied().collect();
+        neighbors.sort();
+        FlyPathState {
+            id: self.id,
+            pdr: self.pdr,
+            link_pdr: self.link_pdr.clone(),
+            neighbors,
+            behavior: format!("{:?}", self.behavior),
+            maintenance: self.maintenance,
+            verbosity: self.verbosity,
+            flood_cache_len: self.precFloodId.len(),
+            pending_events: self.pending_events.len(),
+        }
+    }
+
+    // Load the catalog again from `catalog_path`, the current catalog is kept if the new one is not valid
+    #[cfg(feature = "modes")]
+    fn reload_catalog(&mut self) -> Result<(), FlyPathError> {
+        let path = self.catalog_path.as_ref().ok_or_else(|| {
+            FlyPathError::InvalidConfig("No catalog path to reload the catalog from".to_string())
+        })?;
+        // the stamp is taken first, so a write during the reload is seen by the next check
+        let stamp = Messages::stamp(path);
+        self.messages = Arc::new(Messages::load_from_file(path)?.into_valid()?);
+        self.catalog_stamp = stamp;
+        Ok(())
+    }
+
+    // Reload the catalog if its modification time or length changed since it was last loaded
+    #[cfg(feature = "modes")]
+    fn watch_catalog(&mut self) {
+        let Some(path) = &self.catalog_path else {
+            return;
+        };
+        // the file can be missing for a moment while an editor saves it
+        let Some(stamp) = Messages::stamp(path) else {
+            return;
+        };
+        if self.catalog_stamp == Some(stamp) {
+            return;
+        }
+        match self.reload_catalog() {
+            Ok(()) => self.send_flypath_reply("ReloadCatalog", "Catalog reloaded"),
+            Err(e) => {
+                // the broken file is reported once, a write that changes its stamp is reloaded again
+                self.catalog_stamp = Some(stamp);
+                self.send_flypath_reply("ReloadCatalog", &format!("Catalog reload failed: {}", e));
+            }
+        }
+    }
+
+    #[cfg(not(feature = "modes"))]
+    fn watch_catalog(&mut self) {}
+
+    // Acknowledge `trigger` with a `Reply` message, truncated to `FLYPATH_MESSAGE_MAX_LEN`
+    fn send_flypath_reply(&mut self, trigger: &str, text: &str) {
+        self.send_flypath_message(FlyPathMessage {
+            node_id: self.id,
+            kind: FlyPathMessageKind::Reply,
+            trigger: trigger.to_string(),
+            theme: None,
+            seq: 0,
+            session_id: None,
+            text: truncate(text, FLYPATH_MESSAGE_MAX_LEN).to_string(),
+        });
+    }
+
+    // Send a FlyPath message with the id and the next sequence number of the drone, on the message
+    // channel if there is one, otherwise to the controller
+    pub(crate) fn send_flypath_message(&mut self, mut message: FlyPathMessage) -> bool {
+        message.node_id = self.id;
+        message.seq = self.message_seq;
+        self.message_seq = self.message_seq.wrapping_add(1);
+        if let Some(message_send) = &self.message_send {
+            // nobody is listening for the messages, the drone keeps running
+            return message_send.send(message).is_ok();
+        }
+        match message.to_events() {
+            Ok(events) => events.into_iter().all(|event| self.send_event(event)),
+            Err(_) => false,
         }
     }
 
@@ -310,11 +722,14 @@ impl FlyPath {
             PacketType::FloodRequest(flood_request) => {
                 let mut updated_flood_request =
                     flood_request.get_incremented(self.id, NodeType::Drone);
+                self.with_behavior(|behavior, ctx| {
+                    behavior.on_flood_request(ctx, &mut updated_flood_request)
+                });
 
-                #[cfg(feature = "modes")]
-                if let FlyPathModes::BrainRot = self.mode {
-                    self.maybe_invalidate_floodRequest(&mut updated_flood_request);
-                }
+                let prev = match flood_request.path_trace.last() {
+                    Some(last_id) => last_id.0,
+                    None => updated_flood_request.initiator_id,
+                };
 
                 if !self.precFloodId.contains(&(
                     updated_flood_request.flood_id,
@@ -331,14 +746,10 @@ impl FlyPath {
                         pack_type: PacketType::FloodRequest(updated_flood_request.clone()),
                     };
 
-                    let prev = match flood_request.path_trace.last() {
-                        Some(last_id) => last_id.0,
-                        None => updated_flood_request.initiator_id,
-                    };
-
                     for (node_id, sender) in &self.packet_send {
-                        if *node_id != prev {
-                            let _ = sender.send(packet_to_send.clone());
+                        if *node_id != prev && sender.send(packet_to_send.clone()).is_ok() {
+                            self.stats
+                                .record_relayed(*node_id, &packet_to_send.pack_type);
                         }
                     }
                     return;
@@ -352,6 +763,8 @@ impl FlyPath {
                         .hops
                         .push(updated_flood_request.initiator_id);
                 }
+                self.stats
+                    .record(Some(prev), |link| link.flood_requests_answered += 1);
                 self.send_packet(&mut response);
             }
             _ => {
@@ -361,21 +774,22 @@ impl FlyPath {
                         return;
                     }
 
-                    #[cfg(feature = "modes")]
-                    if let FlyPathModes::BrainRot = self.mode {
-                        self.pdr = rand::thread_rng().gen_range(0.0..1.0);
-                    }
+                    self.with_behavior(|behavior, ctx| behavior.on_packet(ctx, &mut packet));
 
                     if let PacketType::MsgFragment(_) = &packet.pack_type {
-                        let should_drop_packet = self.pdr > rand::thread_rng().gen_range(0.0..1.0);
+                        let should_drop_packet =
+                            self.with_behavior(|behavior, ctx| behavior.should_drop(ctx, &packet));
                         if should_drop_packet {
                             // Drop the fragment and send Dropped Event
+                            self.stats.record(Self::previous_hop(&packet), |link| {
+                                link.fragments_dropped += 1
+                            });
                             self.send_nack(&packet, NackType::Dropped);
                             return;
                         }
                     }
                     // The packet is a `Nack`, `Ack`, `FloodResponse` or a non dropped `MsgFragment`
-                    self.send_packet(&mut packet);
+                    self.forward_packet(&mut packet);
                 }
             }
         }
@@ -406,55 +820,41 @@ impl FlyPath {
         None
     }
 
+    // Send a packet of another node and update the statistics if it reached the next hop
+    fn forward_packet(&mut self, packet: &mut Packet) {
+        if self.send_packet(packet) {
+            if let Some(next_hop) = packet.routing_header.current_hop() {
+                self.stats.record_relayed(next_hop, &packet.pack_type);
+            }
+        }
+    }
+
     // *Increment the hop_index* and if all is ok send message
     // Send `Nack` if there is no next hop or next hop sender
-    fn send_packet(&mut self, packet: &mut Packet) {
+    // Returns `true` if the packet has been sent to the next hop
+    fn send_packet(&mut self, packet: &mut Packet) -> bool {
         packet.routing_header.increase_hop_index();
         if let Some(next_hop) = packet.routing_header.current_hop() {
-            #[cfg(feature = "modes")]
-            if let FlyPathModes::BrainRot = &self.mode {
-                // Change path or invalidate the content
-                let mut rng = rand::thread_rng();
-
-                let should_change_path_or_invalidate_content = 50 > rng.gen_range(0..100);
-                if should_change_path_or_invalidate_content {
-                    self.change_hops_randomly(&mut packet.routing_header);
-                    self.brainRot_event_flypath_message("PacketSent");
-                } else if let PacketType::MsgFragment(frag) = &mut packet.pack_type {
-                    self.invalidate_packet_content(frag);
-                    self.brainRot_event_flypath_message("PacketSentPANIC");
-                }
-
-                if let Some(sender) = self.packet_send.get_mut(&next_hop) {
-                    if sender.send(packet.clone()).is_err() {
-                        packet.routing_header.decrease_hop_index();
-                        self.send_nack(packet, NackType::ErrorInRouting(next_hop));
-                    } else if let PacketType::MsgFragment(_) = packet.pack_type {
-                        let event = DroneEvent::PacketSent(packet.clone());
-                        self.send_event(event);
-                    }
-                }
-                return;
-            }
+            self.with_behavior(|behavior, ctx| behavior.on_forward(ctx, packet));
 
             if let Some(sender) = self.packet_send.get_mut(&next_hop) {
                 if sender.send(packet.clone()).is_err() {
                     self.packet_send.remove(&next_hop);
                     packet.routing_header.decrease_hop_index();
                     self.send_nack(packet, NackType::ErrorInRouting(next_hop));
-                } else if let PacketType::MsgFragment(_) = packet.pack_type {
-                    let event = DroneEvent::PacketSent(packet.clone());
-
-                    #[cfg(feature = "modes")]
-                    self.event_flypath_message(&event);
-
-                    self.send_event(event);
+                } else {
+                    if let PacketType::MsgFragment(_) = packet.pack_type {
+                        self.with_behavior(|behavior, ctx| behavior.on_packet_sent(ctx, packet));
+                        self.send_event(DroneEvent::PacketSent(packet.clone()));
+                    }
+                    return true;
                 }
             } else {
                 packet.routing_header.decrease_hop_index();
                 self.send_nack(packet, NackType::ErrorInRouting(next_hop));
             }
         }
+        false
     }
 
     // Create and send a nack if the packet is a MsgFragment and send the event to `Controller` otherwise forward to Controller to send to destination
@@ -469,6 +869,9 @@ impl FlyPath {
             // FloodRequest can't enter in this function
             PacketType::FloodRequest(_) => {}
             PacketType::MsgFragment(fragment) => {
+                self.stats
+                    .record_nack_generated(Self::previous_hop(packet), &nack_type);
+
                 // Reverse route
                 let routing_header = SourceRoutingHeader::initialize(self.reverse_hops(packet));
 
@@ -484,82 +887,110 @@ impl FlyPath {
                     nack,
                 ));
 
-                // Behavior depending on the mode
-                match &self.mode {
-                    FlyPathModes::Default => {
-                        self.send_event(DroneEvent::PacketDropped(packet.clone()))
-                    }
-                    #[cfg(feature = "modes")]
-                    FlyPathModes::Spicy(_theme) => {
-                        let event = DroneEvent::PacketDropped(packet.clone());
-                        self.event_flypath_message(&event);
-                        self.send_event(event);
-                    }
-                    #[cfg(feature = "modes")]
-                    FlyPathModes::BrainRot => {
-                        let event = DroneEvent::PacketDropped(packet.clone());
-                        self.brainRot_event_flypath_message("PacketDropped");
-                        self.send_event(event);
-                    }
-                }
+                self.dropped_nack = Some(nack_type);
+                self.with_behavior(|behavior, ctx| behavior.on_packet_dropped(ctx, packet));
+                self.dropped_nack = None;
+                self.send_event(DroneEvent::PacketDropped(packet.clone()));
             }
             _ => {
                 // If an error occurs on a FloodResponse, Ack, Nack send to the client/server throw ControllerShortcut
-                if let Err(e) = self
-                    .controller_send
-                    .send(DroneEvent::ControllerShortcut(packet.clone()))
-                {
-                    // DOCUMENT THIS: Panic if no controller is listening or channel is dropped
-                    panic!("No controller listening or Channel dropped: {}", e);
+                if self.send_event(DroneEvent::ControllerShortcut(packet.clone())) {
+                    self.stats.record(packet.routing_header.next_hop(), |link| {
+                        link.controller_shortcuts += 1
+                    });
                 }
             }
         }
     }
 
-    fn send_event(&self, event: DroneEvent) {
-        if self.controller_send.send(event).is_err() {
-            panic!("Controller is unreaceable");
+    // Returns `true` if the event reached the controller, otherwise applies the controller failure policy.
+    // When the policy stops the drone, `try_run` returns once the current packet or command has been handled
+    pub(crate) fn send_event(&mut self, event: DroneEvent) -> bool {
+        if let ControllerFailurePolicy::Buffer { limit } = self.controller_failure {
+            return self.buffer_event(event, limit);
         }
+        let event = match self.controller_send.send(event) {
+            Ok(()) => return true,
+            Err(e) => e.into_inner(),
+        };
+
+        self.undelivered_events += 1;
+        match self.controller_failure {
+            ControllerFailurePolicy::LogAndDrop => {
+                eprintln!(
+                    "FlyPath {}: controller unreachable, dropped {:?}",
+                    self.id, event
+                );
+            }
+            _ => self.controller_failed = true,
+        }
+        false
     }
 
-    fn reverse_hops(&self, packet: &Packet) -> Vec<NodeId> {
-        let mut reverse_hops = packet.routing_header.hops.clone();
-        reverse_hops.truncate(packet.routing_header.hop_index);
-        reverse_hops.insert(packet.routing_header.hop_index, self.id);
-        reverse_hops.reverse();
-        reverse_hops
+    // `ControllerFailurePolicy::Buffer`: the events wait while the bounded channel of the controller is
+    // full and are sent, in order, before the next event. A disconnected controller never comes back,
+    // so it stops the drone as with `Stop`.
+    fn buffer_event(&mut self, event: DroneEvent, limit: usize) -> bool {
+        self.pending_events.push_back(event);
+        if self.flush_pending_events() {
+            return true;
+        }
+        if !self.controller_failed && self.pending_events.len() > limit {
+            self.pending_events.pop_front();
+            self.undelivered_events += 1;
+            self.controller_failed = true;
+        }
+        false
     }
 
-    #[cfg(feature = "modes")]
-    fn change_hops_randomly(&self, routing_header: &mut SourceRoutingHeader) {
-        let hop_index = routing_header.hop_index;
-        // Ensure we have enough hops to modify
-        if hop_index + 1 < routing_header.hops.len() {
-            // Extract the portion of hops to be randomized
-            let remaining_hops = &mut routing_header.hops[(hop_index + 1)..];
-
-            // Shuffle the remaining hops
-            remaining_hops.shuffle(&mut rand::thread_rng());
+    // Send the buffered events in order until the channel of the controller is full, returns `true` if
+    // none is left. A disconnected controller stops the drone
+    fn flush_pending_events(&mut self) -> bool {
+        while let Some(event) = self.pending_events.pop_front() {
+            match self.controller_send.try_send(event) {
+                Ok(()) => {}
+                Err(TrySendError::Full(event)) => {
+                    self.pending_events.push_front(event);
+                    return false;
+                }
+                Err(TrySendError::Disconnected(event)) => {
+                    self.pending_events.push_front(event);
+                    self.controller_failed = true;
+                    return false;
+                }
+            }
         }
+        true
     }
 
-    #[cfg(feature = "modes")]
-    fn invalidate_packet_content(&mut self, fragment: &mut Fragment) {
-        fragment.data.shuffle(&mut rand::thread_rng());
+    // Before the drone stops, give the controller `PENDING_EVENTS_EXIT_TIMEOUT` to receive the buffered
+    // events. The events still buffered never reach it, so the drone stops as with `Stop`
+    fn drain_pending_events(&mut self) {
+        let deadline = Instant::now() + PENDING_EVENTS_EXIT_TIMEOUT;
+        while let Some(event) = self.pending_events.pop_front() {
+            if let Err(e) = self.controller_send.send_deadline(event, deadline) {
+                self.pending_events.push_front(e.into_inner());
+                self.controller_failed = true;
+                return;
+            }
+        }
     }
 
-    #[cfg(feature = "modes")]
-    fn maybe_invalidate_floodRequest(&mut self, fReq: &mut FloodRequest) {
-        let mut rng = rand::thread_rng();
+    // The node that sent the packet to the drone
+    fn previous_hop(packet: &Packet) -> Option<NodeId> {
+        packet
+            .routing_header
+            .hop_index
+            .checked_sub(1)
+            .and_then(|index| packet.routing_header.hops.get(index).copied())
+    }
 
-        let should_change_initialator_or_path_trace = 50 > rng.gen_range(0..100);
-        if should_change_initialator_or_path_trace {
-            self.brainRot_event_flypath_message("ChangeInitiatorId");
-            fReq.initiator_id = rng.gen_range(0..31);
-        } else {
-            self.brainRot_event_flypath_message("ShufflePathTrace");
-            fReq.path_trace.shuffle(&mut rng);
-        }
+    fn reverse_hops(&self, packet: &Packet) -> Vec<NodeId> {
+        let mut reverse_hops = packet.routing_header.hops.clone();
+        reverse_hops.truncate(packet.routing_header.hop_index);
+        reverse_hops.insert(packet.routing_header.hop_index, self.id);
+        reverse_hops.reverse();
+        reverse_hops
     }
 }
 
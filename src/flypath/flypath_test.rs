@@ -1,10 +1,17 @@
 #[cfg(test)]
 mod tests {
+    use crate::behavior::{DroneBehavior, DroneContext};
+    use crate::builder::FlyPathBuilder;
+    use crate::command::{FlyPathCommand, FlyPathReply};
+    use crate::error::FlyPathError;
     use crate::flypath::*;
     #[cfg(feature = "modes")]
-    use crate::messages::extract_flypath_message;
-    use crossbeam_channel::unbounded;
+    use crate::flypath_message::extract_flypath_message;
+    use crate::flypath_message::{FlyPathMessage, FlyPathMessageExtractor, FlyPathMessageKind};
+    use crate::stats::NackKind;
+    use crossbeam_channel::{bounded, unbounded};
     use crossbeam_channel::{Receiver, Sender};
+    use std::collections::HashMap;
     use std::thread::{self, sleep};
     use std::time::Duration;
     use wg_2024::controller::{DroneCommand, DroneEvent};
@@ -184,8 +191,7 @@ mod tests {
         let (drone_packet_send, test_packet_recv) = unbounded();
         let (client_sender, client_reciver) = unbounded();
         (
-            FlyPath::new_with_mode(
-                FlyPathModes::BrainRot,
+            FlyPathBuilder::new(
                 1,
                 drone_event_send,
                 drone_command_recv,
@@ -193,8 +199,11 @@ mod tests {
                 vec![(2, drone_packet_send), (3, client_sender)]
                     .into_iter()
                     .collect(),
-                pdr,
-            ),
+            )
+            .pdr(pdr)
+            .mode(FlyPathModes::BrainRot)
+            .build()
+            .unwrap(),
             test_event_recv,
             test_command_send,
             test_packet_recv,
@@ -644,11 +653,805 @@ mod tests {
         test_packet_send.send(packet).unwrap();
 
         drop(test_packet_send);
+        // the drone may already have stopped because the packet channel is disconnected
+        let _ = test_command_send.send(DroneCommand::Crash);
+
+        assert!(handler.join().is_ok());
+    }
+
+    #[test]
+    fn test_crashing_state() {
+        let (
+            mut drone,
+            test_event_recv,
+            test_command_send,
+            test_packet_recv,
+            test_packet_send,
+            client_reciver,
+        ) = setup_test_drone(0.0);
+
+        let handler = thread::spawn(move || {
+            drone.run();
+        });
         test_command_send.send(DroneCommand::Crash).unwrap();
+        sleep(Duration::from_millis(200));
+
+        // the drone is crashing but still reachable: fragments are nacked
+        test_packet_send
+            .send(Packet::new_fragment(
+                SourceRoutingHeader::with_first_hop(vec![3, 1, 2]),
+                1,
+                Fragment::from_string(0, 1, "crash".to_string()),
+            ))
+            .unwrap();
+        let nack = client_reciver.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(
+            nack.pack_type,
+            PacketType::Nack(wg_2024::packet::Nack {
+                fragment_index: 0,
+                nack_type: NackType::ErrorInRouting(1)
+            })
+        );
 
+        // acks are still forwarded
+        let ack = Packet::new_ack(SourceRoutingHeader::with_first_hop(vec![3, 1, 2]), 1, 0);
+        test_packet_send.send(ack.clone()).unwrap();
+        let forwarded = test_packet_recv
+            .recv_timeout(Duration::from_secs(1))
+            .unwrap();
+        assert_eq!(forwarded.pack_type, ack.pack_type);
+
+        // after RemoveSender the ack can only reach 2 through the controller
+        test_command_send
+            .send(DroneCommand::RemoveSender(2))
+            .unwrap();
+        sleep(Duration::from_millis(200));
+        test_packet_send.send(ack.clone()).unwrap();
+        // at most a few events, each one within a second, so a regression fails instead of hanging
+        let shortcut =
+            std::iter::from_fn(|| test_event_recv.recv_timeout(Duration::from_secs(1)).ok())
+                .take(10)
+                .find(|event| matches!(event, DroneEvent::ControllerShortcut(_)));
+        assert!(shortcut.is_some());
+
+        // the drone stops only when all the senders are disconnected
+        assert!(!handler.is_finished());
+        drop(test_packet_send);
         assert!(handler.join().is_ok());
     }
 
+    #[test]
+    fn test_crash_deadline() {
+        let (drone_event_send, _test_event_recv) = unbounded();
+        let (test_command_send, drone_command_recv) = unbounded();
+        let (_test_packet_send, drone_packet_recv) = unbounded();
+        let mut drone = FlyPathBuilder::new(
+            1,
+            drone_event_send,
+            drone_command_recv,
+            drone_packet_recv,
+            HashMap::new(),
+        )
+        .crash_deadline(Duration::from_millis(200))
+        .build()
+        .unwrap();
+
+        let (done_send, done_recv) = unbounded();
+        thread::spawn(move || {
+            drone.run();
+            done_send.send(()).unwrap();
+        });
+        test_command_send.send(DroneCommand::Crash).unwrap();
+
+        // `_test_packet_send` is still connected, the deadline stops the drone
+        assert!(done_recv.recv_timeout(Duration::from_secs(2)).is_ok());
+    }
+
+    #[test]
+    fn test_try_run_exit_reasons() {
+        // the controller drops the command sender
+        let (mut drone, _event_recv, command_send, _packet_recv, _packet_send, _client) =
+            setup_test_drone(0.0);
+        drop(command_send);
+        assert_eq!(drone.try_run().unwrap(), ExitReason::ControllerDisconnected);
+
+        // all the packet senders are dropped
+        let (mut drone, _event_recv, _command_send, _packet_recv, packet_send, _client) =
+            setup_test_drone(0.0);
+        drop(packet_send);
+        assert_eq!(
+            drone.try_run().unwrap(),
+            ExitReason::PacketChannelDisconnected
+        );
+
+        // crash and then the packet senders are dropped
+        let (mut drone, _event_recv, command_send, _packet_recv, packet_send, _client) =
+            setup_test_drone(0.0);
+        command_send.send(DroneCommand::Crash).unwrap();
+        drop(packet_send);
+        assert_eq!(drone.try_run().unwrap(), ExitReason::Crashed);
+
+        // the controller drops the event receiver before a PacketSent
+        let (mut drone, event_recv, _command_send, _packet_recv, packet_send, _client) =
+            setup_test_drone(0.0);
+        drop(event_recv);
+        packet_send
+            .send(Packet::new_fragment(
+                SourceRoutingHeader::with_first_hop(vec![3, 1, 2]),
+                1,
+                Fragment::from_string(0, 1, "bye".to_string()),
+            ))
+            .unwrap();
+        assert!(matches!(
+            drone.try_run(),
+            Err(FlyPathError::ControllerUnreachable)
+        ));
+    }
+
+    #[test]
+    fn test_controller_failure_policy() {
+        fn run_without_controller(
+            policy: ControllerFailurePolicy,
+            fragments: u64,
+        ) -> Result<ExitReason, FlyPathError> {
+            let (drone_event_send, test_event_recv) = unbounded();
+            let (_test_command_send, drone_command_recv) = unbounded();
+            let (test_packet_send, drone_packet_recv) = unbounded();
+            let (drone_packet_send, _test_packet_recv) = unbounded();
+            let mut drone = FlyPathBuilder::new(
+                1,
+                drone_event_send,
+                drone_command_recv,
+                drone_packet_recv,
+                vec![(2, drone_packet_send)].into_iter().collect(),
+            )
+            .controller_failure(policy)
+            .build()
+            .unwrap();
+
+            // every forwarded fragment generates a PacketSent event
+            drop(test_event_recv);
+            for fragment_index in 0..fragments {
+                test_packet_send
+                    .send(Packet::new_fragment(
+                        SourceRoutingHeader::with_first_hop(vec![3, 1, 2]),
+                        1,
+                        Fragment::from_string(fragment_index, fragments, "lost".to_string()),
+                    ))
+                    .unwrap();
+            }
+            drop(test_packet_send);
+            drone.try_run()
+        }
+
+        assert!(matches!(
+            run_without_controller(ControllerFailurePolicy::Panic, 3),
+            Err(FlyPathError::ControllerUnreachable)
+        ));
+        assert_eq!(
+            run_without_controller(ControllerFailurePolicy::Stop, 3).unwrap(),
+            ExitReason::ControllerUnreachable {
+                policy: ControllerFailurePolicy::Stop,
+                undelivered: 1
+            }
+        );
+        // a disconnected controller never comes back, there is nothing to buffer for
+        let buffer = ControllerFailurePolicy::Buffer { limit: 2 };
+        assert_eq!(
+            run_without_controller(buffer.clone(), 3).unwrap(),
+            ExitReason::ControllerUnreachable {
+                policy: buffer,
+                undelivered: 1
+            }
+        );
+        assert_eq!(
+            run_without_controller(ControllerFailurePolicy::LogAndDrop, 3).unwrap(),
+            ExitReason::PacketChannelDisconnected
+        );
+    }
+
+    #[test]
+    fn test_controller_failure_buffer_full_channel() {
+        let (drone_event_send, test_event_recv) = bounded(1);
+        let (_test_command_send, drone_command_recv) = unbounded();
+        let (_test_packet_send, drone_packet_recv) = unbounded();
+        let mut drone = FlyPathBuilder::new(
+            1,
+            drone_event_send,
+            drone_command_recv,
+            drone_packet_recv,
+            HashMap::new(),
+        )
+        .controller_failure(ControllerFailurePolicy::Buffer { limit: 1 })
+        .build()
+        .unwrap();
+        let event = |session_id| {
+            DroneEvent::PacketSent(Packet::new_ack(
+                SourceRoutingHeader::with_first_hop(vec![1, 2]),
+                session_id,
+                0,
+            ))
+        };
+        let session_id = |event: DroneEvent| match event {
+            DroneEvent::PacketSent(packet) => packet.session_id,
+            _ => panic!("unexpected event"),
+        };
+
+        // the channel is full: the event waits instead of blocking the drone
+        assert!(drone.send_event(event(1)));
+        assert!(!drone.send_event(event(2)));
+        assert_eq!(drone.pending_events.len(), 1);
+
+        // once the controller reads, the buffered event is sent first
+        assert_eq!(session_id(test_event_recv.recv().unwrap()), 1);
+        assert!(!drone.send_event(event(3)));
+        assert_eq!(session_id(test_event_recv.recv().unwrap()), 2);
+        assert!(!drone.controller_failed);
+
+        // more than `limit` events waiting stop the drone
+        assert!(!drone.send_event(event(4)));
+        assert!(!drone.controller_failed);
+        assert!(!drone.send_event(event(5)));
+        assert!(drone.controller_failed);
+        assert_eq!(drone.undelivered_events, 1);
+    }
+
+    #[test]
+    fn test_controller_failure_buffer_idle_drone() {
+        let (drone_event_send, test_event_recv) = bounded(1);
+        let (_test_command_send, drone_command_recv) = unbounded();
+        let (test_packet_send, drone_packet_recv) = unbounded();
+        let (drone_packet_send, _test_packet_recv) = unbounded();
+        let mut drone = FlyPathBuilder::new(
+            1,
+            drone_event_send,
+            drone_command_recv,
+            drone_packet_recv,
+            vec![(2, drone_packet_send)].into_iter().collect(),
+        )
+        .controller_failure(ControllerFailurePolicy::Buffer { limit: 4 })
+        .build()
+        .unwrap();
+
+        // every forwarded fragment generates a PacketSent event: one fills the channel, two wait
+        for fragment_index in 0..3 {
+            test_packet_send
+                .send(Packet::new_fragment(
+                    SourceRoutingHeader::with_first_hop(vec![3, 1, 2]),
+                    1,
+                    Fragment::from_string(fragment_index, 3, "idle".to_string()),
+                ))
+                .unwrap();
+        }
+        let handle = thread::spawn(move || drone.try_run());
+        sleep(Duration::from_millis(200));
+
+        // no other packet arrives, the buffered events are delivered anyway
+        for fragment_index in 0..3 {
+            match test_event_recv.recv_timeout(Duration::from_secs(1)) {
+                Ok(DroneEvent::PacketSent(Packet {
+                    pack_type: PacketType::MsgFragment(fragment),
+                    ..
+                })) => assert_eq!(fragment.fragment_index, fragment_index),
+                event => panic!("expected a PacketSent event, got {:?}", event),
+            }
+        }
+        drop(test_packet_send);
+        assert_eq!(
+            handle.join().unwrap().unwrap(),
+            ExitReason::PacketChannelDisconnected
+        );
+    }
+
+    // Custom behavior: refuses to crash and drops every fragment
+    #[derive(Debug, Clone)]
+    struct Stubborn;
+
+    impl DroneBehavior for Stubborn {
+        fn on_command(&mut self, _ctx: &mut DroneContext, cmd: &DroneCommand) -> bool {
+            !matches!(cmd, DroneCommand::Crash)
+        }
+
+        fn should_drop(&mut self, _ctx: &mut DroneContext, _packet: &Packet) -> bool {
+            true
+        }
+
+        fn clone_box(&self) -> Box<dyn DroneBehavior> {
+            Box::new(self.clone())
+        }
+    }
+
+    #[test]
+    fn test_custom_behavior() {
+        let (drone_event_send, _test_event_recv) = unbounded();
+        let (test_command_send, drone_command_recv) = unbounded();
+        let (test_packet_send, drone_packet_recv) = unbounded();
+        let (drone_packet_send, _test_packet_recv) = unbounded();
+        let (client_send, client_recv) = unbounded();
+        let mut drone = FlyPathBuilder::new(
+            1,
+            drone_event_send,
+            drone_command_recv,
+            drone_packet_recv,
+            vec![(2, drone_packet_send), (3, client_send)]
+                .into_iter()
+                .collect(),
+        )
+        .behavior(Stubborn)
+        .build()
+        .unwrap();
+
+        test_command_send.send(DroneCommand::Crash).unwrap();
+        test_packet_send
+            .send(Packet::new_fragment(
+                SourceRoutingHeader::with_first_hop(vec![3, 1, 2]),
+                1,
+                Fragment::from_string(0, 1, "stubborn".to_string()),
+            ))
+            .unwrap();
+        drop(test_packet_send);
+
+        // the crash has been refused and the fragment dropped even with pdr 0
+        assert_eq!(
+            drone.try_run().unwrap(),
+            ExitReason::PacketChannelDisconnected
+        );
+        let nack = client_recv.try_recv().unwrap();
+        assert_eq!(
+            nack.pack_type,
+            PacketType::Nack(wg_2024::packet::Nack {
+                fragment_index: 0,
+                nack_type: NackType::Dropped
+            })
+        );
+    }
+
+    // Spawn a drone that receives FlyPath commands, returns the command senders and the event receiver
+    fn spawn_with_commands(
+        builder: impl FnOnce(FlyPathBuilder) -> FlyPathBuilder,
+    ) -> (
+        Sender<FlyPathCommand>,
+        Sender<DroneCommand>,
+        Receiver<DroneEvent>,
+    ) {
+        let (drone_event_send, test_event_recv) = unbounded();
+        let (test_command_send, drone_command_recv) = unbounded();
+        let (test_flypath_send, drone_flypath_recv) = unbounded();
+        let (test_packet_send, drone_packet_recv) = unbounded::<Packet>();
+        let mut drone = builder(
+            FlyPathBuilder::new(
+                1,
+                drone_event_send,
+                drone_command_recv,
+                drone_packet_recv,
+                HashMap::new(),
+            )
+            .commands(drone_flypath_recv),
+        )
+        .build()
+        .unwrap();
+        thread::spawn(move || {
+            let _test_packet_send = test_packet_send;
+            drone.run();
+        });
+        (test_flypath_send, test_command_send, test_event_recv)
+    }
+
+    #[cfg(feature = "modes")]
+    fn recv_flypath_message(event_recv: &Receiver<DroneEvent>) -> Option<String> {
+        recv_typed_flypath_message(event_recv).map(|message| message.text)
+    }
+
+    // Receive the next FlyPath message, joining its parts
+    fn recv_typed_flypath_message(event_recv: &Receiver<DroneEvent>) -> Option<FlyPathMessage> {
+        let mut extractor = FlyPathMessageExtractor::default();
+        loop {
+            let event = event_recv.recv_timeout(Duration::from_secs(1)).ok()?;
+            if let Some(message) = extractor.push_message(&event) {
+                return Some(message);
+            }
+        }
+    }
+
+    #[test]
+    fn test_flypath_command_set_mode() {
+        let (flypath_send, _command_send, event_recv) = spawn_with_commands(|builder| builder);
+
+        flypath_send
+            .send(FlyPathCommand::SetMode(FlyPathModes::Default))
+            .unwrap();
+        let reply = recv_typed_flypath_message(&event_recv).unwrap();
+        assert_eq!(reply.text, "Mode switched to Default");
+        assert_eq!(reply.kind, FlyPathMessageKind::Reply);
+        assert_eq!(reply.trigger, "SetMode");
+        assert_eq!(reply.seq, 0);
+    }
+
+    #[test]
+    fn test_messages_channel() {
+        let (message_send, message_recv) = unbounded();
+        let (flypath_send, _command_send, event_recv) =
+            spawn_with_commands(|builder| builder.messages_channel(message_send));
+
+        flypath_send
+            .send(FlyPathCommand::SetMode(FlyPathModes::Default))
+            .unwrap();
+        let reply: FlyPathMessage = message_recv.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(reply.kind, FlyPathMessageKind::Reply);
+        assert_eq!(reply.text, "Mode switched to Default");
+        // the controller only receives protocol events
+        assert!(event_recv.recv_timeout(Duration::from_millis(200)).is_err());
+
+        #[cfg(feature = "modes")]
+        {
+            let (message_send, message_recv) = unbounded();
+            let (_flypath_send, command_send, event_recv) = spawn_with_commands(|builder| {
+                builder
+                    .theme(FlyPathThemes::Batman)
+                    .messages_channel(message_send)
+            });
+            command_send.send(DroneCommand::RemoveSender(2)).unwrap();
+            let themed = message_recv.recv_timeout(Duration::from_secs(1)).unwrap();
+            assert_eq!(themed.kind, FlyPathMessageKind::Command);
+            assert_eq!(themed.trigger, "RemoveSender");
+            assert!(event_recv.recv_timeout(Duration::from_millis(200)).is_err());
+        }
+    }
+
+    #[cfg(feature = "modes")]
+    #[test]
+    fn test_selection_state_per_drone() {
+        let catalog = r#"{
+            "spicy": {
+                "Batman": {
+                    "RemoveSender": {"strategy": "round-robin", "messages": ["First", "Second"]}
+                }
+            },
+            "brainrot": {}
+        }"#;
+        let spawn = || {
+            spawn_with_commands(|builder| {
+                builder
+                    .theme(FlyPathThemes::Batman)
+                    .messages(Messages::from_json_str(catalog).unwrap())
+            })
+        };
+        let (_flypath_send_1, command_send_1, event_recv_1) = spawn();
+        let (_flypath_send_2, command_send_2, event_recv_2) = spawn();
+
+        for expected in ["First", "Second", "First"] {
+            command_send_1.send(DroneCommand::RemoveSender(2)).unwrap();
+            assert_eq!(
+                recv_flypath_message(&event_recv_1).as_deref(),
+                Some(expected)
+            );
+        }
+        // the second drone starts from the beginning of the list
+        command_send_2.send(DroneCommand::RemoveSender(2)).unwrap();
+        assert_eq!(
+            recv_flypath_message(&event_recv_2).as_deref(),
+            Some("First")
+        );
+    }
+
+    #[cfg(feature = "modes")]
+    #[test]
+    fn test_language() {
+        let catalog = r#"{
+            "spicy": {
+                "Batman": {
+                    "RemoveSender": {"en": ["Gone"], "it": ["Andato"]},
+                    "AddSender": {"en": ["Welcome"]}
+                }
+            },
+            "brainrot": {}
+        }"#;
+        let (_flypath_send, command_send, event_recv) = spawn_with_commands(|builder| {
+            builder
+                .theme(FlyPathThemes::Batman)
+                .messages(Messages::from_json_str(catalog).unwrap())
+                .language("it")
+        });
+
+        command_send.send(DroneCommand::RemoveSender(2)).unwrap();
+        assert_eq!(recv_flypath_message(&event_recv).as_deref(), Some("Andato"));
+        // no Italian variant, the fallback language is used
+        let (sender, _receiver) = unbounded();
+        command_send
+            .send(DroneCommand::AddSender(5, sender))
+            .unwrap();
+        assert_eq!(
+            recv_flypath_message(&event_recv).as_deref(),
+            Some("Welcome")
+        );
+    }
+
+    #[cfg(feature = "modes")]
+    #[test]
+    fn test_templated_messages() {
+        let catalog = Messages::from_json_str(
+            r#"{
+                "spicy": {
+                    "Batman": {
+                        "RemoveSender": ["Drone {node_id} lost {neighbor} {{sad}}"],
+                        "SetPacketDropRate": ["pdr {pdr}, forwarded {packets_forwarded}"]
+                    }
+                },
+                "brainrot": {}
+            }"#,
+        )
+        .unwrap();
+        let (_flypath_send, command_send, event_recv) =
+            spawn_with_commands(|builder| builder.theme(FlyPathThemes::Batman).messages(catalog));
+
+        command_send.send(DroneCommand::RemoveSender(7)).unwrap();
+        assert_eq!(
+            recv_flypath_message(&event_recv).as_deref(),
+            Some("Drone 1 lost 7 {sad}")
+        );
+        command_send
+            .send(DroneCommand::SetPacketDropRate(0.25))
+            .unwrap();
+        assert_eq!(
+            recv_flypath_message(&event_recv).as_deref(),
+            Some("pdr 0.25, forwarded 0")
+        );
+    }
+
+    #[cfg(feature = "modes")]
+    #[test]
+    fn test_typed_flypath_messages() {
+        let (flypath_send, command_send, event_recv) =
+            spawn_with_commands(|builder| builder.theme(FlyPathThemes::Batman));
+
+        command_send.send(DroneCommand::RemoveSender(2)).unwrap();
+        let themed = recv_typed_flypath_message(&event_recv).unwrap();
+        assert_eq!(themed.node_id, 1);
+        assert_eq!(themed.kind, FlyPathMessageKind::Command);
+        assert_eq!(themed.trigger, "RemoveSender");
+        assert_eq!(themed.theme.as_deref(), Some("Batman"));
+        assert_eq!(themed.seq, 0);
+        assert_eq!(themed.session_id, None);
+
+        flypath_send
+            .send(FlyPathCommand::SetMode(FlyPathModes::BrainRot))
+            .unwrap();
+        let reply = recv_typed_flypath_message(&event_recv).unwrap();
+        assert_eq!(reply.kind, FlyPathMessageKind::Reply);
+        assert_eq!(reply.theme, None);
+        assert_eq!(reply.seq, 1);
+    }
+
+    #[cfg(feature = "modes")]
+    #[test]
+    fn test_flypath_command_switch_theme_and_reload() {
+        let (flypath_send, command_send, event_recv) = spawn_with_commands(|builder| builder);
+
+        // Default mode: no themed message for the command
+        command_send.send(DroneCommand::RemoveSender(2)).unwrap();
+        flypath_send
+            .send(FlyPathCommand::SetTheme(FlyPathThemes::Batman))
+            .unwrap();
+        assert_eq!(
+            recv_flypath_message(&event_recv).as_deref(),
+            Some("Theme switched to Batman")
+        );
+
+        // Spicy mode: the same command has a themed message
+        command_send.send(DroneCommand::RemoveSender(2)).unwrap();
+        let themed = recv_flypath_message(&event_recv).unwrap();
+        assert!(themed.len() <= 128);
+
+        // the drone has been built with the embedded catalog
+        flypath_send.send(FlyPathCommand::ReloadCatalog).unwrap();
+        assert!(recv_flypath_message(&event_recv)
+            .unwrap()
+            .starts_with("Catalog reload failed"));
+
+        let (flypath_send, _command_send, event_recv) =
+            spawn_with_commands(|builder| builder.catalog_path("resources/messages.json"));
+        flypath_send.send(FlyPathCommand::ReloadCatalog).unwrap();
+        assert_eq!(
+            recv_flypath_message(&event_recv).as_deref(),
+            Some("Catalog reloaded")
+        );
+    }
+
+    #[cfg(feature = "modes")]
+    #[test]
+    fn test_flypath_command_custom_theme() {
+        let catalog = Messages::from_json_str(
+            r#"{
+                "spicy": { "Star Wars": { "RemoveSender": ["These aren't the drones you're looking for"] } },
+                "brainrot": {}
+            }"#,
+        )
+        .unwrap();
+        let (flypath_send, command_send, event_recv) =
+            spawn_with_commands(|builder| builder.messages(catalog));
+
+        flypath_send
+            .send(FlyPathCommand::SetTheme(FlyPathThemes::Custom(
+                "Jar Jar".to_string(),
+            )))
+            .unwrap();
+        assert_eq!(
+            recv_flypath_message(&event_recv).as_deref(),
+            Some("Theme switch failed: Unknown theme: Jar Jar")
+        );
+
+        flypath_send
+            .send(FlyPathCommand::SetTheme(FlyPathThemes::Custom(
+                "Star Wars".to_string(),
+            )))
+            .unwrap();
+        assert_eq!(
+            recv_flypath_message(&event_recv).as_deref(),
+            Some("Theme switched to Star Wars")
+        );
+        command_send.send(DroneCommand::RemoveSender(2)).unwrap();
+        assert_eq!(
+            recv_flypath_message(&event_recv).as_deref(),
+            Some("These aren't the drones you're looking for")
+        );
+    }
+
+    #[test]
+    fn test_flypath_command_control_plane() {
+        // 3 - d1 - 2
+        let (drone_event_send, _test_event_recv) = unbounded();
+        let (_test_command_send, drone_command_recv) = unbounded();
+        let (test_flypath_send, drone_flypath_recv) = unbounded();
+        let (drone_reply_send, test_reply_recv) = unbounded();
+        let (test_packet_send, drone_packet_recv) = unbounded();
+        let (neighbor_send, neighbor_recv) = unbounded();
+        let (client_send, client_recv) = unbounded();
+        let mut drone = FlyPathBuilder::new(
+            1,
+            drone_event_send,
+            drone_command_recv,
+            drone_packet_recv,
+            vec![(2, neighbor_send), (3, client_send)]
+                .into_iter()
+                .collect(),
+        )
+        .commands(drone_flypath_recv)
+        .replies(drone_reply_send)
+        .build()
+        .unwrap();
+        thread::spawn(move || drone.run());
+
+        let fragment = Packet::new_fragment(
+            SourceRoutingHeader::with_first_hop(vec![3, 1, 2]),
+            1,
+            Fragment::from_string(0, 1, "link".to_string()),
+        );
+        let recv_reply = || {
+            test_reply_recv
+                .recv_timeout(Duration::from_secs(1))
+                .unwrap()
+        };
+        let recv_nack = || match client_recv.recv_timeout(Duration::from_secs(1)) {
+            Ok(Packet {
+                pack_type: PacketType::Nack(nack),
+                ..
+            }) => nack.nack_type,
+            packet => panic!("expected a nack, got {:?}", packet),
+        };
+
+        test_flypath_send.send(FlyPathCommand::QueryStats).unwrap();
+        assert!(matches!(recv_reply(), FlyPathReply::Stats(_)));
+
+        // the pdr of the link to 2 overrides the pdr of the drone
+        test_flypath_send
+            .send(FlyPathCommand::SetLinkPdr {
+                neighbor: 2,
+                pdr: Some(1.0),
+            })
+            .unwrap();
+        assert!(matches!(recv_reply(), FlyPathReply::Done));
+        test_packet_send.send(fragment.clone()).unwrap();
+        assert_eq!(recv_nack(), NackType::Dropped);
+
+        test_flypath_send
+            .send(FlyPathCommand::SetLinkPdr {
+                neighbor: 2,
+                pdr: Some(1.5),
+            })
+            .unwrap();
+        assert!(matches!(
+            recv_reply(),
+            FlyPathReply::Failed(FlyPathError::InvalidConfig(_))
+        ));
+
+        test_flypath_send
+            .send(FlyPathCommand::SetLinkPdr {
+                neighbor: 2,
+                pdr: None,
+            })
+            .unwrap();
+        assert!(matches!(recv_reply(), FlyPathReply::Done));
+        test_packet_send.send(fragment.clone()).unwrap();
+        assert!(neighbor_recv.recv_timeout(Duration::from_secs(1)).is_ok());
+
+        // in maintenance fragments are nacked as if the drone were crashing
+        test_flypath_send
+            .send(FlyPathCommand::EnterMaintenance)
+            .unwrap();
+        assert!(matches!(recv_reply(), FlyPathReply::Done));
+        test_packet_send.send(fragment.clone()).unwrap();
+        assert_eq!(recv_nack(), NackType::ErrorInRouting(1));
+
+        // but flood requests are still forwarded
+        let flood_request = FloodRequest::initialize(7, 3, NodeType::Client);
+        test_packet_send
+            .send(Packet::new_flood_request(
+                SourceRoutingHeader::initialize(Vec::new()),
+                1,
+                flood_request.clone(),
+            ))
+            .unwrap();
+        match neighbor_recv.recv_timeout(Duration::from_secs(1)) {
+            Ok(Packet {
+                pack_type: PacketType::FloodRequest(forwarded),
+                ..
+            }) => assert_eq!(
+                forwarded.path_trace,
+                flood_request.get_incremented(1, NodeType::Drone).path_trace
+            ),
+            packet => panic!("expected a flood request, got {:?}", packet),
+        }
+
+        test_flypath_send.send(FlyPathCommand::DumpState).unwrap();
+        let FlyPathReply::State(state) = recv_reply() else {
+            panic!("expected the state of the drone");
+        };
+        assert_eq!(state.id, 1);
+        assert_eq!(state.neighbors, vec![2, 3]);
+        assert!(state.maintenance);
+        assert!(state.link_pdr.is_empty());
+
+        test_flypath_send
+            .send(FlyPathCommand::LeaveMaintenance)
+            .unwrap();
+        assert!(matches!(recv_reply(), FlyPathReply::Done));
+        test_packet_send.send(fragment).unwrap();
+        assert!(neighbor_recv.recv_timeout(Duration::from_secs(1)).is_ok());
+    }
+
+    #[cfg(feature = "modes")]
+    #[test]
+    fn test_flypath_command_verbosity() {
+        let (flypath_send, command_send, event_recv) =
+            spawn_with_commands(|builder| builder.theme(FlyPathThemes::Batman));
+
+        flypath_send
+            .send(FlyPathCommand::SetVerbosity(
+                crate::command::MessageVerbosity::Off,
+            ))
+            .unwrap();
+        assert_eq!(
+            recv_flypath_message(&event_recv).as_deref(),
+            Some("Verbosity set to Off")
+        );
+
+        // no themed message for the command
+        command_send.send(DroneCommand::RemoveSender(2)).unwrap();
+        assert_eq!(recv_flypath_message(&event_recv), None);
+
+        flypath_send
+            .send(FlyPathCommand::SetVerbosity(
+                crate::command::MessageVerbosity::Commands,
+            ))
+            .unwrap();
+        assert_eq!(
+            recv_flypath_message(&event_recv).as_deref(),
+            Some("Verbosity set to Commands")
+        );
+        command_send.send(DroneCommand::RemoveSender(2)).unwrap();
+        assert!(recv_flypath_message(&event_recv).is_some());
+    }
+
     #[cfg(feature = "modes")]
     #[test]
     fn test_crash_brainrot() {
@@ -823,4 +1626,316 @@ mod tests {
         assert!(messages.is_some());
         println!("{:?}", messages);
     }
+
+    #[test]
+    fn test_builder_validation() {
+        let (controller_send, _controller_recv) = unbounded();
+        let (_command_send, command_recv) = unbounded();
+        let (packet_send, packet_recv) = unbounded();
+        let builder = FlyPathBuilder::new(
+            1,
+            controller_send,
+            command_recv,
+            packet_recv,
+            vec![(2, packet_send.clone())].into_iter().collect(),
+        );
+
+        assert!(matches!(
+            builder.clone().pdr(1.5).build(),
+            Err(FlyPathError::InvalidConfig(_))
+        ));
+        assert!(builder.clone().pdr(-0.1).build().is_err());
+        assert!(builder.clone().pdr(f32::NAN).build().is_err());
+
+        let drone = builder.clone().pdr(0.3).seed(1).build();
+        assert!(drone.as_ref().is_ok_and(|drone| drone.pdr == 0.3));
+
+        // the drone can not be its own neighbor
+        let (controller_send, _controller_recv) = unbounded();
+        let (_command_send, command_recv) = unbounded();
+        let (packet_send, packet_recv) = unbounded();
+        let result = FlyPathBuilder::new(
+            1,
+            controller_send,
+            command_recv,
+            packet_recv,
+            vec![(1, packet_send)].into_iter().collect(),
+        )
+        .build();
+        assert!(matches!(result, Err(FlyPathError::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn test_drone_new_corrects_config() {
+        let (controller_send, _controller_recv) = unbounded();
+        let (_command_send, command_recv) = unbounded();
+        let (packet_send, packet_recv) = unbounded();
+        let neighbors = vec![(1, packet_send.clone()), (2, packet_send)]
+            .into_iter()
+            .collect();
+
+        // the controllers of wg_2024 can pass any pdr, the drone must not panic
+        let drone = <FlyPath as Drone>::new(
+            1,
+            controller_send,
+            command_recv,
+            packet_recv,
+            neighbors,
+            1.5,
+        );
+        assert_eq!(drone.pdr, 1.0);
+        assert!(!drone.packet_send.contains_key(&1));
+        assert!(drone.packet_send.contains_key(&2));
+    }
+
+    #[cfg(feature = "modes")]
+    #[test]
+    fn test_builder_catalog_missing_file() {
+        let (controller_send, _controller_recv) = unbounded();
+        let (_command_send, command_recv) = unbounded();
+        let (_packet_send, packet_recv) = unbounded();
+
+        let result = FlyPathBuilder::new(
+            1,
+            controller_send,
+            command_recv,
+            packet_recv,
+            HashMap::new(),
+        )
+        .theme(FlyPathThemes::Batman)
+        .catalog_path("non_existent_catalog.json")
+        .build();
+        assert!(matches!(result, Err(FlyPathError::Io { .. })));
+    }
+
+    #[cfg(feature = "modes")]
+    #[test]
+    fn test_watch_catalog() {
+        let catalog = std::fs::read_to_string("resources/messages.json").unwrap();
+        let path = std::env::temp_dir().join(format!("flypath_watch_{}.json", std::process::id()));
+        // the modification time is set explicitly, so the test does not depend on the mtime resolution
+        let write_at = |content: &str, secs: u64| {
+            std::fs::write(&path, content).unwrap();
+            std::fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(std::time::UNIX_EPOCH + Duration::from_secs(secs))
+                .unwrap();
+        };
+        write_at(&catalog, 1_000);
+        let path_str = path.to_str().unwrap().to_string();
+
+        // the catalog can only be watched if it is loaded from a path
+        let (controller_send, _controller_recv) = unbounded();
+        let (_command_send, command_recv) = unbounded();
+        let (_packet_send, packet_recv) = unbounded();
+        let builder = FlyPathBuilder::new(
+            1,
+            controller_send,
+            command_recv,
+            packet_recv,
+            HashMap::new(),
+        );
+        assert!(matches!(
+            builder
+                .clone()
+                .watch_catalog(Duration::from_millis(50))
+                .build(),
+            Err(FlyPathError::InvalidConfig(_))
+        ));
+        assert!(matches!(
+            builder
+                .catalog_path(&path_str)
+                .watch_catalog(Duration::ZERO)
+                .build(),
+            Err(FlyPathError::InvalidConfig(_))
+        ));
+
+        let (_flypath_send, command_send, event_recv) = spawn_with_commands(|builder| {
+            builder
+                .theme(FlyPathThemes::Batman)
+                .catalog_path(&path_str)
+                .watch_catalog(Duration::from_millis(50))
+        });
+        // the catalog is not reloaded if the file does not change
+        assert_eq!(recv_flypath_message(&event_recv), None);
+
+        write_at("{ broken", 2_000);
+        assert!(recv_flypath_message(&event_recv)
+            .unwrap()
+            .starts_with("Catalog reload failed"));
+        // the drone keeps the previous catalog
+        command_send.send(DroneCommand::RemoveSender(2)).unwrap();
+        assert!(recv_flypath_message(&event_recv).is_some());
+
+        // the complete write has the same modification time of the broken one, but not the same length
+        write_at(&catalog, 2_000);
+        assert_eq!(
+            recv_flypath_message(&event_recv).as_deref(),
+            Some("Catalog reloaded")
+        );
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[cfg(feature = "modes")]
+    #[test]
+    fn test_theme_from_str() {
+        assert!(matches!(
+            "Dark Souls".parse::<FlyPathThemes>(),
+            Ok(FlyPathThemes::DarkSouls)
+        ));
+        assert!(matches!(
+            "HarryPotter".parse::<FlyPathThemes>(),
+            Ok(FlyPathThemes::HarryPotter)
+        ));
+        assert!(matches!(
+            "Skibidi".parse::<FlyPathThemes>(),
+            Err(FlyPathError::UnknownTheme(name)) if name == "Skibidi"
+        ));
+    }
+
+    // Feed the same fragments to a seeded drone and collect everything it produced
+    type TestDrone = (
+        FlyPath,
+        Receiver<DroneEvent>,
+        Sender<DroneCommand>,
+        Receiver<Packet>,
+        Sender<Packet>,
+        Receiver<Packet>,
+    );
+    type SeededOutput = (Vec<Packet>, Vec<Packet>, Vec<DroneEvent>);
+
+    fn run_seeded(setup: TestDrone, seed: u64) -> SeededOutput {
+        let (drone, test_event_recv, _, test_packet_recv, _, client_reciver) = setup;
+        let mut drone = drone.with_seed(seed);
+        for i in 0..50 {
+            drone.packet_handler(Packet::new_fragment(
+                SourceRoutingHeader::with_first_hop(vec![3, 1, 2, 4]),
+                1,
+                Fragment::from_string(i, 50, "seeded".to_string()),
+            ));
+        }
+        (
+            test_packet_recv.try_iter().collect(),
+            client_reciver.try_iter().collect(),
+            test_event_recv.try_iter().collect(),
+        )
+    }
+
+    #[test]
+    fn test_seeded_drops_are_deterministic() {
+        let first = run_seeded(setup_test_drone(0.5), 42);
+        let second = run_seeded(setup_test_drone(0.5), 42);
+
+        // with pdr 0.5 some fragments are forwarded and some are dropped
+        assert!(!first.0.is_empty());
+        assert!(!first.1.is_empty());
+        assert_eq!(first, second);
+    }
+
+    #[cfg(feature = "modes")]
+    #[test]
+    fn test_seeded_brainrot_is_deterministic() {
+        let first = run_seeded(setup_test_drone_brainrot(0.0), 7);
+        let second = run_seeded(setup_test_drone_brainrot(0.0), 7);
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_stats_counters() {
+        let (
+            mut drone,
+            _test_event_recv,
+            _test_command_send,
+            _test_packet_recv,
+            _test_packet_send,
+            _client_reciver,
+        ) = setup_test_drone(0.0);
+        let stats = drone.stats();
+
+        // forwarded fragment from 3 to 2
+        drone.packet_handler(Packet::new_fragment(
+            SourceRoutingHeader::with_first_hop(vec![3, 1, 2]),
+            1,
+            Fragment::from_string(0, 1, "stats".to_string()),
+        ));
+        // relayed ack from 2 to 3
+        drone.packet_handler(Packet::new_ack(
+            SourceRoutingHeader::with_first_hop(vec![2, 1, 3]),
+            1,
+            0,
+        ));
+        // next hop 4 is not a neighbor
+        drone.packet_handler(Packet::new_fragment(
+            SourceRoutingHeader::with_first_hop(vec![3, 1, 4]),
+            1,
+            Fragment::from_string(0, 1, "stats".to_string()),
+        ));
+        // dropped fragment from 3
+        drone.pdr = 1.0;
+        drone.packet_handler(Packet::new_fragment(
+            SourceRoutingHeader::with_first_hop(vec![3, 1, 2]),
+            1,
+            Fragment::from_string(1, 2, "stats".to_string()),
+        ));
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.total.fragments_forwarded, 1);
+        assert_eq!(stats.fragments_forwarded(), 1);
+        assert_eq!(snapshot.total.fragments_dropped, 1);
+        assert_eq!(snapshot.total.acks_relayed, 1);
+        // the generated nacks are sent to 3 but are not relayed
+        assert_eq!(snapshot.total.nacks_relayed, 0);
+        assert_eq!(snapshot.total.nacks_generated(NackKind::Dropped), 1);
+        assert_eq!(snapshot.total.nacks_generated(NackKind::ErrorInRouting), 1);
+
+        let to_2 = snapshot.neighbor(2).unwrap();
+        assert_eq!(to_2.fragments_forwarded, 1);
+        let from_3 = snapshot.neighbor(3).unwrap();
+        assert_eq!(from_3.acks_relayed, 1);
+        assert_eq!(from_3.fragments_dropped, 1);
+        assert_eq!(from_3.nacks_generated(NackKind::Dropped), 1);
+    }
+
+    #[test]
+    fn test_stats_snapshot_from_another_thread() {
+        let (
+            mut d1,
+            mut d2,
+            d1_send,
+            _d2_send,
+            _c1_recv,
+            _from_d1_event_recv,
+            _from_d2_event_recv,
+            _d1_command_send,
+            _d2_command_send,
+        ) = setup_connected(0.0, 0.0);
+        let d1_stats = d1.stats();
+        let d2_stats = d2.stats();
+
+        thread::spawn(move || {
+            d1.run();
+        });
+        thread::spawn(move || {
+            d2.run();
+        });
+
+        let flood_request = FloodRequest::initialize(1, 10, NodeType::Client);
+        d1_send
+            .send(Packet::new_flood_request(
+                SourceRoutingHeader::initialize(Vec::new()),
+                1,
+                flood_request,
+            ))
+            .unwrap();
+        sleep(Duration::from_secs(1));
+
+        // c1 - d1 - d2: d1 forwards to d2, d2 answers and d1 relays the response to c1
+        let d1_snapshot = d1_stats.snapshot();
+        assert_eq!(d1_snapshot.total.flood_requests_forwarded, 1);
+        assert_eq!(d1_snapshot.neighbor(2).unwrap().flood_requests_forwarded, 1);
+        assert_eq!(d1_snapshot.neighbor(10).unwrap().flood_responses_relayed, 1);
+        assert_eq!(d2_stats.snapshot().total.flood_requests_answered, 1);
+    }
 }
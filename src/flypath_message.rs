@@ -0,0 +1,710 @@
+use crate::error::FlyPathError;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use wg_2024::{
+    controller::DroneEvent,
+    network::{NodeId, SourceRoutingHeader},
+    packet::{Fragment, Packet, PacketType, FRAGMENT_DSIZE},
+};
+
+/// Longest text that can be sent as a FlyPath message, split across several fragments
+pub const FLYPATH_MESSAGE_MAX_LEN: usize = 16 * FRAGMENT_DSIZE;
+
+// Prefix of the payload of every FlyPath fragment, followed by the checksum
+const MAGIC: [u8; 4] = [0xF1, 0x7A, 0x9A, 0x7E];
+// Bytes of a message carried by a single FlyPath fragment, after the magic prefix and the checksum
+pub(crate) const PART_DSIZE: usize = FRAGMENT_DSIZE - MAGIC.len() - 4;
+
+/// Longest text sent by `Messages::generate_droneEvent_to_controller`, which must fit inside a single
+/// fragment after the magic prefix and the checksum. Longer messages need the multi-fragment encoding,
+/// see `FlyPathMessage::to_events`.
+pub const FLYPATH_SINGLE_FRAGMENT_LEN: usize = PART_DSIZE;
+
+/// Encode `text` as a special FlyPath message sent by the drone `node_id`.
+///
+/// The message is a `DroneEvent::PacketSent` of a `MsgFragment` with invalid parameters:
+/// - `session_id == u64::MAX`
+/// - `routing_header.hop_index == usize::MAX` and `routing_header.hops == [node_id]`
+/// - `fragment_index == u64::MAX` and `total_n_fragments == 0`
+///
+/// and a payload that starts with a magic prefix and a checksum, see `flypath_part`.
+///
+/// # Returns
+/// - `Err(FlyPathError::MessageTooLong)`: if `text` is longer than `FLYPATH_SINGLE_FRAGMENT_LEN`
+#[cfg(feature = "modes")]
+pub(crate) fn flypath_event(node_id: NodeId, text: &str) -> Result<DroneEvent, FlyPathError> {
+    let bytes = text.as_bytes();
+    if bytes.len() > PART_DSIZE {
+        return Err(FlyPathError::MessageTooLong {
+            len: bytes.len(),
+            max: PART_DSIZE,
+        });
+    }
+    Ok(flypath_part(node_id, u64::MAX, 0, bytes))
+}
+
+// Encode `payload` as one or more FlyPath messages sent by the drone `node_id`.
+//
+// A payload that fits inside a `Fragment` is encoded as in `flypath_event`, a longer one is split in
+// `n` parts with `fragment_index` from `0` to `n - 1` and `total_n_fragments == n`, that
+// `FlyPathMessageExtractor` joins again.
+fn flypath_events(node_id: NodeId, payload: &[u8]) -> Vec<DroneEvent> {
+    if payload.len() <= PART_DSIZE {
+        return vec![flypath_part(node_id, u64::MAX, 0, payload)];
+    }
+    let total = payload.len().div_ceil(PART_DSIZE) as u64;
+    payload
+        .chunks(PART_DSIZE)
+        .enumerate()
+        .map(|(index, chunk)| flypath_part(node_id, index as u64, total, chunk))
+        .collect()
+}
+
+/// What a FlyPath message is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlyPathMessageKind {
+    /// Themed message for a `DroneEvent`, e.g. `PacketSent`
+    Event,
+    /// Themed message for a `DroneCommand`, e.g. `Crash`
+    Command,
+    /// Message describing a BrainRot action, e.g. `ShufflePathTrace` or `NoCrash`
+    BrainRot,
+    /// Acknowledgement of a `FlyPathCommand` or of a catalog reload
+    Reply,
+    /// Message without header, sent by `Messages::generate_droneEvent_to_controller`
+    Text,
+}
+
+impl FlyPathMessageKind {
+    fn code(self) -> u8 {
+        match self {
+            FlyPathMessageKind::Event => 0,
+            FlyPathMessageKind::Command => 1,
+            FlyPathMessageKind::BrainRot => 2,
+            FlyPathMessageKind::Reply => 3,
+            FlyPathMessageKind::Text => 4,
+        }
+    }
+
+    fn from_code(code: u8) -> Option<Self> {
+        match code {
+            0 => Some(FlyPathMessageKind::Event),
+            1 => Some(FlyPathMessageKind::Command),
+            2 => Some(FlyPathMessageKind::BrainRot),
+            3 => Some(FlyPathMessageKind::Reply),
+            4 => Some(FlyPathMessageKind::Text),
+            _ => None,
+        }
+    }
+}
+
+/// A FlyPath message with its header, see `extract_flypath_message_typed`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlyPathMessage {
+    /// drone that sent the message
+    pub node_id: NodeId,
+    pub kind: FlyPathMessageKind,
+    /// event, command, BrainRot action or FlyPath command that triggered the message, e.g. `"Crash"`,
+    /// `"ShufflePathTrace"` or `"SetMode"`; empty for `FlyPathMessageKind::Text`
+    pub trigger: String,
+    /// theme of the message, `None` outside the `Spicy` mode
+    pub theme: Option<String>,
+    /// sequence number of the message between the ones sent by the drone
+    pub seq: u32,
+    /// session of the packet that triggered the message
+    pub session_id: Option<u64>,
+    pub text: String,
+}
+
+// First byte of a payload with header, it is never the first byte of an UTF-8 text
+const HEADER_MARKER: u8 = 0xFF;
+const HAS_THEME: u8 = 1;
+const HAS_SESSION: u8 = 2;
+
+impl FlyPathMessage {
+    /// Encode the message as the `DroneEvent`s to send to the controller, more than one if the
+    /// message does not fit inside a `Fragment`.
+    ///
+    /// The payload starts with `0xFF` and the length of the text (2 bytes, little endian), then the text
+    /// and the header:
+    /// - the kind and the sequence number (4 bytes, little endian)
+    /// - a flags byte, then the theme if present and the trigger, each one prefixed by its length
+    /// - the session id if present (8 bytes, little endian)
+    ///
+    /// Theme and trigger are truncated to 255 bytes. The text comes first so that `extract_flypath_message`
+    /// reads a text of up to `FLYPATH_SINGLE_FRAGMENT_LEN - 3` bytes from the first fragment, even when
+    /// the header does not fit in it.
+    ///
+    /// # Returns
+    /// - `Err(FlyPathError::MessageTooLong)`: if `text` is longer than `FLYPATH_MESSAGE_MAX_LEN`
+    pub fn to_events(&self) -> Result<Vec<DroneEvent>, FlyPathError> {
+        if self.text.len() > FLYPATH_MESSAGE_MAX_LEN {
+            return Err(FlyPathError::MessageTooLong {
+                len: self.text.len(),
+                max: FLYPATH_MESSAGE_MAX_LEN,
+            });
+        }
+        if self.kind == FlyPathMessageKind::Text {
+            return Ok(flypath_events(self.node_id, self.text.as_bytes()));
+        }
+
+        let mut payload = vec![HEADER_MARKER];
+        payload.extend_from_slice(&(self.text.len() as u16).to_le_bytes());
+        payload.extend_from_slice(self.text.as_bytes());
+        payload.push(self.kind.code());
+        payload.extend_from_slice(&self.seq.to_le_bytes());
+        let mut flags = 0;
+        if self.theme.is_some() {
+            flags |= HAS_THEME;
+        }
+        if self.session_id.is_some() {
+            flags |= HAS_SESSION;
+        }
+        payload.push(flags);
+        if let Some(theme) = &self.theme {
+            push_short_str(&mut payload, theme);
+        }
+        push_short_str(&mut payload, &self.trigger);
+        if let Some(session_id) = self.session_id {
+            payload.extend_from_slice(&session_id.to_le_bytes());
+        }
+        Ok(flypath_events(self.node_id, &payload))
+    }
+
+    // Decode the payload of a FlyPath message, a payload without header is a `Text` message
+    fn decode(node_id: NodeId, payload: &[u8]) -> Option<Self> {
+        let Some((&HEADER_MARKER, mut rest)) = payload.split_first() else {
+            return Some(FlyPathMessage {
+                node_id,
+                kind: FlyPathMessageKind::Text,
+                trigger: String::new(),
+                theme: None,
+                seq: 0,
+                session_id: None,
+                text: String::from_utf8_lossy(payload).to_string(),
+            });
+        };
+        let text = take_text(&mut rest)?;
+        let kind = FlyPathMessageKind::from_code(*take(&mut rest, 1)?.first()?)?;
+        let seq = u32::from_le_bytes(take(&mut rest, 4)?.try_into().ok()?);
+        let flags = *take(&mut rest, 1)?.first()?;
+        let theme = if flags & HAS_THEME != 0 {
+            Some(take_short_str(&mut rest)?)
+        } else {
+            None
+        };
+        let trigger = take_short_str(&mut rest)?;
+        let session_id = if flags & HAS_SESSION != 0 {
+            Some(u64::from_le_bytes(take(&mut rest, 8)?.try_into().ok()?))
+        } else {
+            None
+        };
+        Some(FlyPathMessage {
+            node_id,
+            kind,
+            trigger,
+            theme,
+            seq,
+            session_id,
+            text,
+        })
+    }
+
+    // The text of a payload with header that starts with it, e.g. the first part of a long message.
+    // `None` if the text does not end inside `payload`
+    fn decode_text(payload: &[u8]) -> Option<String> {
+        let (&HEADER_MARKER, mut rest) = payload.split_first()? else {
+            return None;
+        };
+        take_text(&mut rest)
+    }
+}
+
+// Split the text, prefixed by its length (2 bytes, little endian), from `rest`
+fn take_text(rest: &mut &[u8]) -> Option<String> {
+    let len = u16::from_le_bytes(take(rest, 2)?.try_into().ok()?);
+    Some(String::from_utf8_lossy(take(rest, len as usize)?).to_string())
+}
+
+// Append `text`, truncated to 255 bytes, prefixed by its length
+fn push_short_str(payload: &mut Vec<u8>, text: &str) {
+    let text = truncate(text, u8::MAX as usize);
+    payload.push(text.len() as u8);
+    payload.extend_from_slice(text.as_bytes());
+}
+
+fn take_short_str(rest: &mut &[u8]) -> Option<String> {
+    let len = *take(rest, 1)?.first()?;
+    Some(String::from_utf8_lossy(take(rest, len as usize)?).to_string())
+}
+
+// Split the first `n` bytes from `rest`
+fn take<'a>(rest: &mut &'a [u8], n: usize) -> Option<&'a [u8]> {
+    if rest.len() < n {
+        return None;
+    }
+    let (taken, remaining) = rest.split_at(n);
+    *rest = remaining;
+    Some(taken)
+}
+
+/// Longest prefix of `text` of at most `max` bytes that ends at a char boundary
+pub(crate) fn truncate(text: &str, max: usize) -> &str {
+    let mut end = text.len().min(max);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+// Build the special fragment that carries `bytes`, at most `PART_DSIZE` long.
+// The payload is the magic prefix, the checksum of the part (4 bytes, little endian) and `bytes`.
+fn flypath_part(
+    node_id: NodeId,
+    fragment_index: u64,
+    total_n_fragments: u64,
+    bytes: &[u8],
+) -> DroneEvent {
+    let checksum = checksum(node_id, fragment_index, total_n_fragments, bytes);
+    let fragment = Fragment {
+        fragment_index,
+        total_n_fragments,
+        length: (MAGIC.len() + 4 + bytes.len()) as u8,
+        data: {
+            let mut data = [0; FRAGMENT_DSIZE];
+            data[..MAGIC.len()].copy_from_slice(&MAGIC);
+            data[MAGIC.len()..MAGIC.len() + 4].copy_from_slice(&checksum.to_le_bytes());
+            data[MAGIC.len() + 4..MAGIC.len() + 4 + bytes.len()].copy_from_slice(bytes);
+            data
+        },
+    };
+
+    let packet = Packet {
+        pack_type: PacketType::MsgFragment(fragment),
+        routing_header: SourceRoutingHeader {
+            hop_index: usize::MAX,
+            hops: vec![node_id], // with this che controller know the sender
+        },
+        session_id: u64::MAX,
+    };
+
+    DroneEvent::PacketSent(packet)
+}
+
+// CRC-32 of the sender, the position of the part and its bytes, so that a payload copied inside
+// another fragment is not valid
+fn checksum(node_id: NodeId, fragment_index: u64, total_n_fragments: u64, bytes: &[u8]) -> u32 {
+    let position = [
+        fragment_index.to_le_bytes(),
+        total_n_fragments.to_le_bytes(),
+    ];
+    let mut crc = !0u32;
+    for byte in [node_id]
+        .iter()
+        .chain(position.iter().flatten())
+        .chain(bytes)
+    {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+// A verified FlyPath fragment, single or part of a longer message
+struct FlyPathPart<'a> {
+    node_id: NodeId,
+    fragment_index: u64,
+    total_n_fragments: u64,
+    bytes: &'a [u8],
+}
+
+impl FlyPathPart<'_> {
+    fn is_single(&self) -> bool {
+        self.total_n_fragments == 0
+    }
+}
+
+// The FlyPath fragment inside `event`, `None` if the sentinels, the magic prefix or the checksum
+// do not match
+fn flypath_part_of(event: &DroneEvent) -> Option<FlyPathPart<'_>> {
+    let DroneEvent::PacketSent(packet) = event else {
+        return None;
+    };
+    let PacketType::MsgFragment(fragment) = &packet.pack_type else {
+        return None;
+    };
+    if packet.session_id != u64::MAX
+        || packet.routing_header.hop_index != usize::MAX
+        || packet.routing_header.hops.len() != 1
+        || fragment.length as usize > FRAGMENT_DSIZE
+    {
+        return None;
+    }
+    let single = fragment.fragment_index == u64::MAX && fragment.total_n_fragments == 0;
+    let part =
+        fragment.total_n_fragments >= 2 && fragment.fragment_index < fragment.total_n_fragments;
+    if !single && !part {
+        return None;
+    }
+
+    let payload = &fragment.data[..fragment.length as usize];
+    let payload = payload.strip_prefix(&MAGIC)?;
+    if payload.len() < 4 {
+        return None;
+    }
+    let (expected, bytes) = payload.split_at(4);
+    let node_id = packet.routing_header.hops[0];
+    let checksum = checksum(
+        node_id,
+        fragment.fragment_index,
+        fragment.total_n_fragments,
+        bytes,
+    );
+    if expected != checksum.to_le_bytes() {
+        return None;
+    }
+    Some(FlyPathPart {
+        node_id,
+        fragment_index: fragment.fragment_index,
+        total_n_fragments: fragment.total_n_fragments,
+        bytes,
+    })
+}
+
+/// Identifies and extracts a FlyPath message from a given `DroneEvent`.
+///
+/// # Returns
+/// - `Some((NodeId, String))`:
+///   - `NodeId`: The identifier of the FlyPath drone operating in spicy mode.
+///   - `String`: The themed message associated with the event.
+/// - `None`: If the event is a normal `DroneEvent` or invalid as a FlyPath event: the sentinel values,
+///   the magic prefix and the checksum of the payload must all match.
+///   A message split across several fragments is returned from its first part when its text fits in it,
+///   i.e. texts of up to `FLYPATH_SINGLE_FRAGMENT_LEN - 3` bytes whose header did not fit, the other parts are
+///   `None`. Use `FlyPathMessageExtractor` to join the longer texts.
+///
+/// # Examples
+/// ```rust
+/// use flyPath::extract_flypath_message;
+/// use wg_2024::controller::DroneEvent;
+///
+/// fn print_event(event: &DroneEvent) {
+///     if let Some((node_id, message)) = extract_flypath_message(event) {
+///         println!("FlyPath Event Detected! Node: {}, Message: {}", node_id, message);
+///     } else {
+///         println!("This is not a FlyPath event.");
+///     }
+/// }
+/// ```
+pub fn extract_flypath_message(event: &DroneEvent) -> Option<(NodeId, String)> {
+    let part = flypath_part_of(event)?;
+    let text = if part.is_single() {
+        FlyPathMessage::decode(part.node_id, part.bytes)?.text
+    } else if part.fragment_index == 0 {
+        FlyPathMessage::decode_text(part.bytes)?
+    } else {
+        return None;
+    };
+    Some((part.node_id, text))
+}
+
+/// Same as `extract_flypath_message` but returns the message with its header: kind, trigger, theme,
+/// sequence number and session.
+///
+/// # Returns
+/// - `None`: If the event is a normal `DroneEvent`, invalid as a FlyPath event or a part of a message
+///   split across several fragments, use `FlyPathMessageExtractor::push_message` to join it.
+pub fn extract_flypath_message_typed(event: &DroneEvent) -> Option<FlyPathMessage> {
+    let part = flypath_part_of(event)?;
+    if !part.is_single() {
+        return None;
+    }
+    FlyPathMessage::decode(part.node_id, part.bytes)
+}
+
+/// Joins the FlyPath messages split across several fragments.
+///
+/// The parts of a message are buffered for each drone until the last one arrives; a sequence
+/// that is not completed within the timeout, or that misses a part, is discarded. Messages that
+/// fit in a single fragment are returned immediately, as with `extract_flypath_message`.
+///
+/// # Example
+/// ```ignore
+/// let mut extractor = FlyPathMessageExtractor::new(Duration::from_secs(1));
+/// for event in controller_recv.iter() {
+///     if let Some((node_id, message)) = extractor.push(&event) {
+///         println!("Drone {}: {}", node_id, message);
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct FlyPathMessageExtractor {
+    timeout: Duration,
+    partial: HashMap<NodeId, PartialMessage>,
+}
+
+#[derive(Debug, Clone)]
+struct PartialMessage {
+    data: Vec<u8>,
+    next_index: u64,
+    total: u64,
+    started: Instant,
+}
+
+impl Default for FlyPathMessageExtractor {
+    /// Incomplete messages are discarded after 5 seconds
+    fn default() -> Self {
+        Self::new(Duration::from_secs(5))
+    }
+}
+
+impl FlyPathMessageExtractor {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            partial: HashMap::new(),
+        }
+    }
+
+    /// Feed a `DroneEvent` to the extractor.
+    ///
+    /// # Returns
+    /// - `Some((NodeId, String))`: the event completes a FlyPath message
+    /// - `None`: the event is a normal `DroneEvent` or a part of a message not yet complete
+    pub fn push(&mut self, event: &DroneEvent) -> Option<(NodeId, String)> {
+        self.push_message(event)
+            .map(|message| (message.node_id, message.text))
+    }
+
+    /// Same as `push` but returns the message with its header, see `extract_flypath_message_typed`
+    pub fn push_message(&mut self, event: &DroneEvent) -> Option<FlyPathMessage> {
+        let part = flypath_part_of(event)?;
+        let node_id = part.node_id;
+        if part.is_single() {
+            // the parts of the previous message will never arrive
+            self.partial.remove(&node_id);
+            return FlyPathMessage::decode(node_id, part.bytes);
+        }
+
+        let now = Instant::now();
+        if part.fragment_index == 0 {
+            self.partial.insert(
+                node_id,
+                PartialMessage {
+                    data: Vec::new(),
+                    next_index: 0,
+                    total: part.total_n_fragments,
+                    started: now,
+                },
+            );
+        }
+        let partial = self.partial.get_mut(&node_id)?;
+        if partial.next_index != part.fragment_index
+            || partial.total != part.total_n_fragments
+            || now.duration_since(partial.started) > self.timeout
+        {
+            self.partial.remove(&node_id);
+            return None;
+        }
+
+        partial.data.extend_from_slice(part.bytes);
+        partial.next_index += 1;
+        if partial.next_index < partial.total {
+            return None;
+        }
+        let partial = self.partial.remove(&node_id)?;
+        FlyPathMessage::decode(node_id, &partial.data)
+    }
+
+    /// Discard the incomplete messages older than the timeout, returns the drones that sent them
+    pub fn expire(&mut self) -> Vec<NodeId> {
+        let now = Instant::now();
+        let expired: Vec<NodeId> = self
+            .partial
+            .iter()
+            .filter(|(_, partial)| now.duration_since(partial.started) > self.timeout)
+            .map(|(node_id, _)| *node_id)
+            .collect();
+        for node_id in &expired {
+            self.partial.remove(node_id);
+        }
+        expired
+    }
+
+    /// Number of drones with an incomplete message
+    pub fn pending(&self) -> usize {
+        self.partial.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread::sleep;
+
+    fn message(node_id: NodeId, text: &str) -> FlyPathMessage {
+        FlyPathMessage {
+            node_id,
+            kind: FlyPathMessageKind::Command,
+            trigger: "Crash".to_string(),
+            theme: Some("Dark Souls".to_string()),
+            seq: 7,
+            session_id: Some(42),
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_single_message() {
+        let events = message(3, "short").to_events().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(
+            extract_flypath_message(&events[0]),
+            Some((3, "short".to_string()))
+        );
+        assert_eq!(
+            extract_flypath_message_typed(&events[0]),
+            Some(message(3, "short"))
+        );
+        let mut extractor = FlyPathMessageExtractor::default();
+        assert_eq!(extractor.push(&events[0]), Some((3, "short".to_string())));
+    }
+
+    #[test]
+    fn test_header() {
+        let mut brainrot = FlyPathMessage {
+            kind: FlyPathMessageKind::BrainRot,
+            trigger: "ShufflePathTrace".to_string(),
+            theme: None,
+            session_id: None,
+            ..message(3, "shuffled")
+        };
+        let events = brainrot.to_events().unwrap();
+        assert_eq!(
+            extract_flypath_message_typed(&events[0]),
+            Some(brainrot.clone())
+        );
+
+        // the legacy encoding has no header
+        brainrot.kind = FlyPathMessageKind::Text;
+        let legacy = brainrot.to_events().unwrap();
+        let text = extract_flypath_message_typed(&legacy[0]).unwrap();
+        assert_eq!(text.kind, FlyPathMessageKind::Text);
+        assert_eq!(text.text, "shuffled");
+        assert!(text.trigger.is_empty());
+
+        // a truncated header is not a FlyPath message
+        let DroneEvent::PacketSent(mut packet) = events[0].clone() else {
+            unreachable!()
+        };
+        if let PacketType::MsgFragment(fragment) = &mut packet.pack_type {
+            fragment.length = 4;
+        }
+        assert_eq!(
+            extract_flypath_message_typed(&DroneEvent::PacketSent(packet)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_magic_and_checksum() {
+        let events = message(3, "checked").to_events().unwrap();
+        let DroneEvent::PacketSent(packet) = events[0].clone() else {
+            unreachable!()
+        };
+        let with_fragment = |change: &dyn Fn(&mut Packet)| {
+            let mut packet = packet.clone();
+            change(&mut packet);
+            extract_flypath_message(&DroneEvent::PacketSent(packet))
+        };
+        let fragment = |packet: &mut Packet| match &mut packet.pack_type {
+            PacketType::MsgFragment(fragment) => fragment.clone(),
+            _ => unreachable!(),
+        };
+        assert!(with_fragment(&|_| {}).is_some());
+
+        // a changed byte
+        assert!(with_fragment(&|packet| {
+            let mut changed = fragment(packet);
+            changed.data[20] ^= 1;
+            packet.pack_type = PacketType::MsgFragment(changed);
+        })
+        .is_none());
+        // the same payload from another drone
+        assert!(with_fragment(&|packet| packet.routing_header.hops = vec![4]).is_none());
+        // the sentinels of a FlyPath message without the magic prefix
+        assert!(with_fragment(&|packet| {
+            packet.pack_type = PacketType::MsgFragment(Fragment {
+                fragment_index: u64::MAX,
+                total_n_fragments: 0,
+                ..Fragment::from_string(0, 1, "checked".to_string())
+            })
+        })
+        .is_none());
+    }
+
+    #[test]
+    fn test_header_in_second_part() {
+        // the text fits in a fragment, the header does not
+        let text = "x".repeat(FLYPATH_SINGLE_FRAGMENT_LEN - 3);
+        let long_header = FlyPathMessage {
+            trigger: "PacketSentPANIC".to_string(),
+            ..message(3, &text)
+        };
+        let events = long_header.to_events().unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(extract_flypath_message(&events[0]), Some((3, text)));
+        assert_eq!(extract_flypath_message(&events[1]), None);
+
+        let mut extractor = FlyPathMessageExtractor::default();
+        assert_eq!(extractor.push_message(&events[0]), None);
+        assert_eq!(extractor.push_message(&events[1]), Some(long_header));
+    }
+
+    #[test]
+    fn test_multi_fragment_message() {
+        let text = "Long ".repeat(60);
+        let events = message(3, &text).to_events().unwrap();
+        assert_eq!(events.len(), 3);
+        // the parts of a text longer than a fragment are not legacy messages
+        assert!(events
+            .iter()
+            .all(|event| extract_flypath_message(event).is_none()));
+
+        let mut extractor = FlyPathMessageExtractor::default();
+        let other = message(4, &text).to_events().unwrap();
+        assert_eq!(extractor.push(&events[0]), None);
+        assert_eq!(extractor.push(&other[0]), None);
+        assert_eq!(extractor.push(&events[1]), None);
+        assert_eq!(extractor.pending(), 2);
+        assert_eq!(extractor.push_message(&events[2]), Some(message(3, &text)));
+        assert_eq!(extractor.pending(), 1);
+
+        // a missing part discards the message
+        assert_eq!(extractor.push(&other[2]), None);
+        assert_eq!(extractor.pending(), 0);
+
+        assert!(matches!(
+            message(3, &"x".repeat(FLYPATH_MESSAGE_MAX_LEN + 1)).to_events(),
+            Err(FlyPathError::MessageTooLong { max, .. }) if max == FLYPATH_MESSAGE_MAX_LEN
+        ));
+    }
+
+    #[test]
+    fn test_extractor_timeout() {
+        let events = message(3, &"Slow ".repeat(60)).to_events().unwrap();
+        let mut extractor = FlyPathMessageExtractor::new(Duration::from_millis(50));
+        assert_eq!(extractor.push(&events[0]), None);
+        sleep(Duration::from_millis(100));
+        assert_eq!(extractor.expire(), vec![3]);
+        assert_eq!(extractor.push(&events[1]), None);
+        assert_eq!(extractor.push(&events[2]), None);
+        assert_eq!(extractor.pending(), 0);
+    }
+}
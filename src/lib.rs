@@ -1,9 +1,39 @@
 #![allow(non_snake_case)]
 
+mod behavior;
+mod builder;
+mod command;
+mod error;
+mod flood_cache;
 pub mod flypath;
+mod flypath_message;
 #[cfg(feature = "modes")]
 mod messages;
+#[cfg(feature = "modes")]
+mod selection;
+mod stats;
+#[cfg(feature = "modes")]
+mod template;
 
+#[cfg(feature = "modes")]
+pub use behavior::{BrainRotBehavior, SpicyBehavior};
+pub use behavior::{DefaultBehavior, DroneBehavior, DroneContext};
+pub use builder::FlyPathBuilder;
+pub use command::{FlyPathCommand, FlyPathReply, FlyPathState, MessageVerbosity};
+pub use error::FlyPathError;
+pub use flood_cache::{FloodCache, FloodCachePolicy};
 pub use flypath::*;
+pub use flypath_message::{
+    extract_flypath_message, extract_flypath_message_typed, FlyPathMessage,
+    FlyPathMessageExtractor, FlyPathMessageKind, FLYPATH_MESSAGE_MAX_LEN,
+    FLYPATH_SINGLE_FRAGMENT_LEN,
+};
+#[cfg(feature = "modes")]
+pub use messages::{
+    CatalogIssue, CatalogLine, CatalogReport, Messages, ThemePacks, BRAINROT_KEYS, SPICY_KEYS,
+};
+#[cfg(feature = "modes")]
+pub use selection::{MessageSelector, SelectionStrategy};
+pub use stats::{FlyPathStats, LinkStats, NackKind, StatsHandle};
 #[cfg(feature = "modes")]
-pub use messages::extract_flypath_message;
+pub use template::{TemplateValues, PLACEHOLDERS};
@@ -1,19 +1,65 @@
-use crate::flypath::FlyPathModes;
+use crate::error::FlyPathError;
+use crate::flypath::{FlyPathModes, FlyPathThemes};
+use crate::flypath_message::{
+    flypath_event, FlyPathMessage, FlyPathMessageKind, FLYPATH_MESSAGE_MAX_LEN,
+    FLYPATH_SINGLE_FRAGMENT_LEN,
+};
+use crate::selection::{self, MessageSelector, SelectionStrategy};
+use crate::template::{self, TemplateValues};
 use rand::Rng;
-use serde::Deserialize;
-use std::{collections::HashMap, fs};
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::{json, Map, Value};
+use std::{
+    collections::{hash_map::Entry, BTreeMap, HashMap},
+    fmt, fs,
+    path::Path,
+    time::SystemTime,
+};
 use wg_2024::{
     controller::{DroneCommand, DroneEvent},
-    network::{NodeId, SourceRoutingHeader},
-    packet::{Fragment, Packet, PacketType, FRAGMENT_DSIZE},
+    network::NodeId,
 };
 
+/// The stock catalog shipped with FlyPath, compiled into the crate so that the drone does not
+/// depend on the working directory of the binary that uses it.
+const DEFAULT_CATALOG: &str = include_str!("../resources/messages.json");
+
+/// Events and commands that can have messages inside a spicy theme
+pub const SPICY_KEYS: [&str; 7] = [
+    "PacketSent",
+    "PacketDropped",
+    "ControllerShortcut",
+    "AddSender",
+    "RemoveSender",
+    "SetPacketDropRate",
+    "Crash",
+];
+
+/// Actions of the BrainRot mode that can have messages
+pub const BRAINROT_KEYS: [&str; 13] = [
+    "Crash",
+    "NoCrash",
+    "AddSender",
+    "NoAddSender",
+    "RemoveSender",
+    "NoRemoveSender",
+    "SetPacketDropRate",
+    "NoSetPacketDropRate",
+    "ChangeInitiatorId",
+    "ShufflePathTrace",
+    "PacketDropped",
+    "PacketSent",
+    "PacketSentPANIC",
+];
+
 /// Rapresent the collection of messages loaded from a JSON file
 ///
 /// # Fields
 /// - `spicy`: first hashmap associates theme names to another map
 ///     - inner map associates event or command names to a list messages
 /// - `brainrot`: hashmap that associate event or command names to a list of messages
+/// - `strategies`: optional, associates theme names (or `"brainrot"`) to the `SelectionStrategy` of their lists
+/// - `fallback_language`: optional, the language used when a list has no variant for the language of the drone, `"en"` by default
 ///
 /// # JSON format has the following structures:
 /// ```json
@@ -24,15 +70,24 @@ use wg_2024::{
 ///             "Event2": ["Message3"]
 ///         },
 ///         "Theme2": {
-///             "Event1": ["Message4"]
+///             "Event1": {"en": ["Message4"], "it": ["Messaggio4"]}
 ///         }
 ///     },
 ///     "brainrot": {
-///         "Event1": ["Message5", "Message6"],
-///         "Event2": ["Message7"]
+///         "Event1": ["Message5", {"text": "Message6", "weight": 3}],
+///         "Event2": {"strategy": "round-robin", "messages": ["Message7", "Message8"]}
+///     },
+///     "strategies": {
+///         "Theme1": "shuffle-bag"
 ///     }
 /// }
-/// ```/// # Notes
+/// ```
+///
+/// A message is a string or an object with its `text` and its `weight` (`1` if not given), a list is an
+/// array of messages or an object with its `strategy` and its `messages`, see `SelectionStrategy`.
+/// An event can also have a list for every language, see `Messages::get_messages_for_language`.
+///
+/// # Notes
 /// - **Case Sensitivity**: Theme names, events, and commands are case-sensitive. Ensure to use the
 ///   auxiliary string conversion functions provided in this struct, such as `Messages::drone_event_to_string`
 ///   or `Messages::drone_command_to_string`, and the `FlyPathThemes` `Display` implementation to retrieve
@@ -40,26 +95,621 @@ use wg_2024::{
 /// - The structure is compatible with the modes and themes defined in the `FlyPathModes` enum.
 #[derive(Debug, Deserialize, Clone)]
 pub struct Messages {
-    spicy: HashMap<String, HashMap<String, Vec<String>>>,
-    brainrot: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    spicy: HashMap<String, HashMap<String, MessageVariants>>,
+    #[serde(default)]
+    brainrot: HashMap<String, MessageVariants>,
+    #[serde(default)]
+    strategies: HashMap<String, SelectionStrategy>,
+    #[serde(default)]
+    fallback_language: Option<String>,
+}
+
+/// Language of the lists when the catalog does not set `fallback_language`
+const DEFAULT_LANGUAGE: &str = "en";
+
+/// A message of the catalog with its weight, used by `SelectionStrategy::Weighted`, and the layer that supplied it
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(from = "RawCatalogMessage")]
+pub(crate) struct CatalogMessage {
+    pub(crate) text: String,
+    pub(crate) weight: u32,
+    pub(crate) source: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawCatalogMessage {
+    Text(String),
+    Weighted {
+        text: String,
+        #[serde(default = "default_weight")]
+        weight: u32,
+    },
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+impl From<RawCatalogMessage> for CatalogMessage {
+    fn from(raw: RawCatalogMessage) -> Self {
+        match raw {
+            RawCatalogMessage::Text(text) => CatalogMessage {
+                text,
+                weight: 1,
+                source: None,
+            },
+            RawCatalogMessage::Weighted { text, weight } => CatalogMessage {
+                text,
+                weight,
+                source: None,
+            },
+        }
+    }
+}
+
+/// A list of messages, with the strategy that overrides the one of its theme
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(from = "RawMessageList")]
+pub(crate) struct MessageList {
+    pub(crate) strategy: Option<SelectionStrategy>,
+    pub(crate) merge: MergeMode,
+    pub(crate) messages: Vec<CatalogMessage>,
+}
+
+/// How a list of a layer is merged with the same list of the previous layers, see `Messages::layer`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum MergeMode {
+    #[default]
+    Replace,
+    Append,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawMessageList {
+    Messages(Vec<CatalogMessage>),
+    WithOptions {
+        strategy: Option<SelectionStrategy>,
+        #[serde(default)]
+        merge: MergeMode,
+        messages: Vec<CatalogMessage>,
+    },
+}
+
+impl MessageList {
+    // Merge the same list of a later layer: its messages replace these or are appended to them
+    fn merge(&mut self, later: MessageList) {
+        match later.merge {
+            MergeMode::Replace => *self = later,
+            MergeMode::Append => {
+                self.strategy = later.strategy.or(self.strategy);
+                self.messages.extend(later.messages);
+            }
+        }
+    }
+}
+
+/// The lists of an event, command or BrainRot action: the list without a language and the list of every language
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(from = "RawMessageVariants")]
+pub(crate) struct MessageVariants {
+    pub(crate) list: Option<MessageList>,
+    pub(crate) languages: HashMap<String, MessageList>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawMessageVariants {
+    List(MessageList),
+    Languages(HashMap<String, MessageList>),
+}
+
+impl From<RawMessageVariants> for MessageVariants {
+    fn from(raw: RawMessageVariants) -> Self {
+        match raw {
+            RawMessageVariants::List(list) => MessageVariants {
+                list: Some(list),
+                languages: HashMap::new(),
+            },
+            RawMessageVariants::Languages(languages) => MessageVariants {
+                list: None,
+                languages,
+            },
+        }
+    }
+}
+
+impl MessageVariants {
+    // Merge the variants of a later layer, list by list
+    fn merge(&mut self, later: MessageVariants) {
+        if let Some(list) = later.list {
+            match &mut self.list {
+                Some(earlier) => earlier.merge(list),
+                None => self.list = Some(list),
+            }
+        }
+        for (language, list) in later.languages {
+            match self.languages.entry(language) {
+                Entry::Occupied(mut earlier) => earlier.get_mut().merge(list),
+                Entry::Vacant(entry) => {
+                    entry.insert(list);
+                }
+            }
+        }
+    }
+
+    // Every list, with and without a language
+    fn lists_mut(&mut self) -> impl Iterator<Item = &mut MessageList> {
+        self.list.iter_mut().chain(self.languages.values_mut())
+    }
+
+    // The first non-empty list of `languages`, otherwise the list without a language
+    fn resolve(&self, languages: &[&str]) -> Option<&MessageList> {
+        languages
+            .iter()
+            .filter_map(|language| self.languages.get(*language))
+            .chain(self.list.as_ref())
+            .find(|list| !list.messages.is_empty())
+    }
+
+    // Every list with its label in the issues of `Messages::validate`, e.g. `Crash` or `Crash (it)`
+    fn labeled(&self, key: &str) -> Vec<(String, &MessageList)> {
+        let mut languages: Vec<(&String, &MessageList)> = self.languages.iter().collect();
+        languages.sort_by_key(|(language, _)| *language);
+        let mut lists: Vec<(String, &MessageList)> = self
+            .list
+            .iter()
+            .map(|list| (key.to_string(), list))
+            .collect();
+        lists.extend(
+            languages
+                .into_iter()
+                .map(|(language, list)| (format!("{} ({})", key, language), list)),
+        );
+        lists
+    }
+}
+
+impl From<RawMessageList> for MessageList {
+    fn from(raw: RawMessageList) -> Self {
+        match raw {
+            RawMessageList::Messages(messages) => MessageList {
+                strategy: None,
+                merge: MergeMode::Replace,
+                messages,
+            },
+            RawMessageList::WithOptions {
+                strategy,
+                merge,
+                messages,
+            } => MessageList {
+                strategy,
+                merge,
+                messages,
+            },
+        }
+    }
 }
 
 impl Messages {
-    /// Load the `Messages` struct from a JSON file at the specified path
-    /// and attemts to parse it to populate the `Message` struct.
+    /// Load the `Messages` struct from a JSON file at the specified path, or from a TOML file if
+    /// the path ends with `.toml`, and attemts to parse it to populate the `Message` struct.
+    /// Every message is marked as supplied by `file_path`, see `Messages::lines`.
     ///
     /// # Returns
     /// - `Ok(Messages)`: The parsed `Message` struct if no error occors
-    /// - `Err(String)`: An error message if the file cannot be read or the JSON is invalid
-    pub fn load_from_file(file_path: &str) -> Result<Self, String> {
-        let file_content = fs::read_to_string(file_path)
-            .map_err(|e| format!("Failed to read the messages file: {}", e))?;
-        serde_json::from_str(&file_content)
-            .map_err(|e| format!("Failed to parse the message JSON: {}", e))
+    /// - `Err(FlyPathError::Io)`: if the file cannot be read
+    /// - `Err(FlyPathError::Parse)`: if the JSON or the TOML is invalid
+    pub fn load_from_file(file_path: &str) -> Result<Self, FlyPathError> {
+        let file_content = fs::read_to_string(file_path).map_err(|source| FlyPathError::Io {
+            path: file_path.to_string(),
+            source,
+        })?;
+        let messages = match Path::new(file_path).extension() {
+            Some(extension) if extension == "toml" => Self::from_toml_str(&file_content),
+            _ => Self::from_json_str(&file_content),
+        };
+        messages.map(|messages| messages.with_source(file_path))
+    }
+
+    /// Load a catalog from a directory of theme packs: every `*.json` (or `*.toml`) file contains the
+    /// events and commands of one theme, named after the file, e.g. `Batman.json` or `Star Wars.json`
+    /// (`DarkSouls.json` is the built-in `"Dark Souls"`), and `brainrot.json` contains the brainrot section.
+    /// The other files are ignored.
+    ///
+    /// Every file is loaded and validated on its own: a file that can not be read, parsed or that has
+    /// errors (see `validate`) is skipped and reported in `ThemePacks::errors`, the others are loaded.
+    /// Files are loaded in the order of their names, a file whose theme has already been loaded from
+    /// another file (e.g. `Pingu.json` and `pingu.toml`, theme names are compared ignoring the case) is
+    /// skipped with `CatalogIssue::DuplicateTheme`.
+    ///
+    /// # Returns
+    /// - `Ok(ThemePacks)`: the catalog of the valid files and the errors of the others
+    /// - `Err(FlyPathError::Io)`: if the directory cannot be read
+    pub fn load_from_dir(dir_path: &str) -> Result<ThemePacks, FlyPathError> {
+        let io_error = |source| FlyPathError::Io {
+            path: dir_path.to_string(),
+            source,
+        };
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(dir_path).map_err(io_error)? {
+            let path = entry.map_err(io_error)?.path();
+            let is_pack = matches!(
+                path.extension().and_then(|extension| extension.to_str()),
+                Some("json" | "toml")
+            );
+            if is_pack && path.is_file() {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+
+        let mut packs = ThemePacks {
+            messages: Self::empty(),
+            errors: Vec::new(),
+        };
+        // lowercase name of every loaded theme (`None` for the brainrot section) and its file
+        let mut loaded: HashMap<Option<String>, String> = HashMap::new();
+        for path in paths {
+            let file_path = path.to_string_lossy().into_owned();
+            let pack = match Self::load_theme_pack(&path).and_then(Self::into_valid) {
+                Ok(pack) => pack,
+                Err(e) => {
+                    packs.errors.push((file_path, e));
+                    continue;
+                }
+            };
+            let theme = pack.spicy.keys().next().cloned();
+            match loaded.entry(theme.as_ref().map(|theme| theme.to_lowercase())) {
+                Entry::Occupied(first) => {
+                    let issue = CatalogIssue::DuplicateTheme {
+                        theme: theme.unwrap_or_else(|| "brainrot".to_string()),
+                        path: first.get().clone(),
+                    };
+                    let report = CatalogReport {
+                        issues: vec![issue],
+                    };
+                    packs
+                        .errors
+                        .push((file_path, FlyPathError::InvalidCatalog(report)));
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(file_path.clone());
+                    packs.messages = packs.messages.layer(pack.with_source(&file_path));
+                }
+            }
+        }
+        Ok(packs)
+    }
+
+    // The catalog with only the theme or the brainrot section of the file at `path`
+    fn load_theme_pack(path: &Path) -> Result<Self, FlyPathError> {
+        let file_content = fs::read_to_string(path).map_err(|source| FlyPathError::Io {
+            path: path.to_string_lossy().into_owned(),
+            source,
+        })?;
+        let lists: HashMap<String, MessageVariants> = match path.extension() {
+            Some(extension) if extension == "toml" => {
+                toml::from_str(&file_content).map_err(|e| FlyPathError::Parse(e.to_string()))?
+            }
+            _ => serde_json::from_str(&file_content)
+                .map_err(|e| FlyPathError::Parse(e.to_string()))?,
+        };
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let mut pack = Self::empty();
+        if name == "brainrot" {
+            pack.brainrot = lists;
+        } else {
+            // a built-in theme can also be named after its variant, e.g. `DarkSouls`
+            let theme = name
+                .parse::<FlyPathThemes>()
+                .map(|theme| theme.to_string())
+                .unwrap_or(name);
+            pack.spicy.insert(theme, lists);
+        }
+        Ok(pack)
+    }
+
+    // A catalog without messages
+    fn empty() -> Self {
+        Messages {
+            spicy: HashMap::new(),
+            brainrot: HashMap::new(),
+            strategies: HashMap::new(),
+            fallback_language: None,
+        }
+    }
+
+    /// Load the embedded catalog, then every file of `file_paths` in order as a layer on top of the
+    /// previous ones, see `Messages::layer`. For example the defaults, then a team-wide override file,
+    /// then the file of a single drone.
+    ///
+    /// # Returns
+    /// - `Err(FlyPathError::Io)` or `Err(FlyPathError::Parse)`: the first file that can not be loaded
+    pub fn load_layers(file_paths: &[&str]) -> Result<Self, FlyPathError> {
+        file_paths
+            .iter()
+            .try_fold(Self::default(), |messages, file_path| {
+                Ok(messages.layer(Self::load_from_file(file_path)?))
+            })
+    }
+
+    /// Merge `later` on top of this catalog, key by key: every list of `later` (for each theme, event or
+    /// BrainRot action and language) replaces the same list of this catalog, or is appended to it if the
+    /// list has `"merge": "append"`. Lists, themes and strategies missing from `later` are kept.
+    pub fn layer(mut self, later: Messages) -> Self {
+        fn merge(
+            earlier: &mut HashMap<String, MessageVariants>,
+            later: HashMap<String, MessageVariants>,
+        ) {
+            for (key, variants) in later {
+                match earlier.entry(key) {
+                    Entry::Occupied(mut entry) => entry.get_mut().merge(variants),
+                    Entry::Vacant(entry) => {
+                        entry.insert(variants);
+                    }
+                }
+            }
+        }
+        for (theme, keys) in later.spicy {
+            merge(self.spicy.entry(theme).or_default(), keys);
+        }
+        merge(&mut self.brainrot, later.brainrot);
+        self.strategies.extend(later.strategies);
+        if later.fallback_language.is_some() {
+            self.fallback_language = later.fallback_language;
+        }
+        self
+    }
+
+    /// Mark every message as supplied by the layer `source`, e.g. the path of its file
+    pub fn with_source(mut self, source: &str) -> Self {
+        let lists = self
+            .spicy
+            .values_mut()
+            .flat_map(HashMap::values_mut)
+            .chain(self.brainrot.values_mut())
+            .flat_map(MessageVariants::lists_mut);
+        for list in lists {
+            for message in list.messages.iter_mut() {
+                message.source = Some(source.to_string());
+            }
+        }
+        self
+    }
+
+    /// Every message of the catalog with the layer that supplied it, sorted by theme (the brainrot
+    /// section last), key and language; the messages of a list keep their order.
+    pub fn lines(&self) -> Vec<CatalogLine> {
+        let sections = self
+            .spicy
+            .iter()
+            .map(|(theme, keys)| (Some(theme), keys))
+            .chain(std::iter::once((None, &self.brainrot)));
+        let mut lines = Vec::new();
+        for (theme, keys) in sections {
+            for (key, variants) in keys {
+                let lists = variants.list.iter().map(|list| (None, list)).chain(
+                    variants
+                        .languages
+                        .iter()
+                        .map(|(language, list)| (Some(language), list)),
+                );
+                for (language, list) in lists {
+                    lines.extend(list.messages.iter().map(|message| CatalogLine {
+                        theme: theme.cloned(),
+                        key: key.clone(),
+                        language: language.cloned(),
+                        text: message.text.clone(),
+                        source: message.source.clone(),
+                    }));
+                }
+            }
+        }
+        // stable sort, the messages of a list keep their order
+        lines.sort_by(|a, b| {
+            (a.theme.is_none(), &a.theme, &a.key, &a.language).cmp(&(
+                b.theme.is_none(),
+                &b.theme,
+                &b.key,
+                &b.language,
+            ))
+        });
+        lines
+    }
+
+    // Modification time and length of the catalog at `file_path`, `None` if it cannot be read.
+    // The length tells a rewrite apart from a partial write on filesystems with a coarse modification time
+    pub(crate) fn stamp(file_path: &str) -> Option<(SystemTime, u64)> {
+        let metadata = fs::metadata(file_path).ok()?;
+        Some((metadata.modified().ok()?, metadata.len()))
+    }
+
+    /// Parse the `Messages` struct from a JSON string with the same format used by `load_from_file`.
+    ///
+    /// # Returns
+    /// - `Ok(Messages)`: The parsed `Message` struct if no error occors
+    /// - `Err(FlyPathError::Parse)`: if the JSON is invalid
+    pub fn from_json_str(json: &str) -> Result<Self, FlyPathError> {
+        serde_json::from_str(json).map_err(|e| FlyPathError::Parse(e.to_string()))
+    }
+
+    /// Parse the `Messages` struct from a TOML string with the same structure of the JSON format, e.g.
+    /// `[spicy.Batman]` followed by `Crash = ["I'm Batman"]`
+    ///
+    /// # Returns
+    /// - `Ok(Messages)`: The parsed `Message` struct if no error occors
+    /// - `Err(FlyPathError::Parse)`: if the TOML is invalid
+    pub fn from_toml_str(toml: &str) -> Result<Self, FlyPathError> {
+        toml::from_str(toml).map_err(|e| FlyPathError::Parse(e.to_string()))
+    }
+
+    // Return the catalog if `validate` finds no errors
+    pub(crate) fn into_valid(self) -> Result<Self, FlyPathError> {
+        let report = self.validate();
+        if report.is_valid() {
+            Ok(self)
+        } else {
+            Err(FlyPathError::InvalidCatalog(report))
+        }
+    }
+
+    /// Check the catalog against the themes, events, commands and BrainRot actions known by FlyPath.
+    ///
+    /// The issues are sorted by theme and key, so the report is stable between calls.
+    /// Missing themes, custom themes and empty lists are only warnings, every other issue is an error.
+    pub fn validate(&self) -> CatalogReport {
+        let mut issues = Vec::new();
+
+        for theme in FlyPathThemes::ALL.iter() {
+            if !self.spicy.contains_key(&theme.to_string()) {
+                issues.push(CatalogIssue::MissingTheme(theme.to_string()));
+            }
+        }
+
+        let mut themes: Vec<&String> = self.spicy.keys().collect();
+        themes.sort();
+        for theme in themes {
+            if !FlyPathThemes::ALL.iter().any(|t| t.to_string() == *theme) {
+                issues.push(CatalogIssue::CustomTheme(theme.clone()));
+            }
+            let mut keys: Vec<(&String, &MessageVariants)> = self.spicy[theme].iter().collect();
+            keys.sort_by_key(|(key, _)| *key);
+            for (key, variants) in keys {
+                if !SPICY_KEYS.contains(&key.as_str()) {
+                    issues.push(CatalogIssue::UnknownKey {
+                        theme: theme.clone(),
+                        key: key.clone(),
+                    });
+                }
+                Self::validate_variants(Some(theme), key, variants, &mut issues);
+            }
+        }
+
+        let mut keys: Vec<(&String, &MessageVariants)> = self.brainrot.iter().collect();
+        keys.sort_by_key(|(key, _)| *key);
+        for (key, variants) in keys {
+            if !BRAINROT_KEYS.contains(&key.as_str()) {
+                issues.push(CatalogIssue::UnknownBrainRotKey(key.clone()));
+            }
+            Self::validate_variants(None, key, variants, &mut issues);
+        }
+
+        let mut sections: Vec<&String> = self.strategies.keys().collect();
+        sections.sort();
+        for section in sections {
+            if section != "brainrot" && !self.spicy.contains_key(section) {
+                issues.push(CatalogIssue::UnknownStrategySection(section.clone()));
+            }
+        }
+
+        CatalogReport { issues }
+    }
+
+    /// Every theme of the catalog sorted by name, the built-in ones as their `FlyPathThemes` variant
+    /// and the others as `FlyPathThemes::Custom`
+    pub fn themes(&self) -> Vec<FlyPathThemes> {
+        let mut names: Vec<&String> = self.spicy.keys().collect();
+        names.sort();
+        names
+            .into_iter()
+            .map(|name| {
+                name.parse()
+                    .unwrap_or_else(|_| FlyPathThemes::Custom(name.clone()))
+            })
+            .collect()
+    }
+
+    /// Find the theme called `name` in the catalog, see `Messages::themes`
+    ///
+    /// # Returns
+    /// - `Ok(FlyPathThemes)`: the built-in theme with that name, otherwise `FlyPathThemes::Custom`
+    /// - `Err(FlyPathError::UnknownTheme)`: if the catalog has no theme called `name`
+    pub fn theme(&self, name: &str) -> Result<FlyPathThemes, FlyPathError> {
+        match name.parse::<FlyPathThemes>() {
+            Ok(theme) if self.has_theme(&theme) => Ok(theme),
+            _ if self.spicy.contains_key(name) => Ok(FlyPathThemes::Custom(name.to_string())),
+            _ => Err(FlyPathError::UnknownTheme(name.to_string())),
+        }
+    }
+
+    // `true` if the catalog has messages for `theme`
+    pub(crate) fn has_theme(&self, theme: &FlyPathThemes) -> bool {
+        self.spicy.contains_key(&theme.to_string())
+    }
+
+    fn validate_variants(
+        theme: Option<&String>,
+        key: &str,
+        variants: &MessageVariants,
+        issues: &mut Vec<CatalogIssue>,
+    ) {
+        let lists = variants.labeled(key);
+        if lists.is_empty() {
+            issues.push(CatalogIssue::EmptyList {
+                theme: theme.cloned(),
+                key: key.to_string(),
+            });
+        }
+        for (label, list) in lists {
+            Self::validate_list(theme, &label, &list.messages, issues);
+        }
+    }
+
+    fn validate_list(
+        theme: Option<&String>,
+        key: &str,
+        messages: &[CatalogMessage],
+        issues: &mut Vec<CatalogIssue>,
+    ) {
+        if messages.is_empty() {
+            issues.push(CatalogIssue::EmptyList {
+                theme: theme.cloned(),
+                key: key.to_string(),
+            });
+        }
+        if !messages.is_empty() && messages.iter().all(|message| message.weight == 0) {
+            issues.push(CatalogIssue::ZeroWeights {
+                theme: theme.cloned(),
+                key: key.to_string(),
+            });
+        }
+        for CatalogMessage { text: message, .. } in messages {
+            if let Err(reason) = template::check(message) {
+                issues.push(CatalogIssue::InvalidTemplate {
+                    theme: theme.cloned(),
+                    key: key.to_string(),
+                    message: message.clone(),
+                    reason,
+                });
+            }
+            if message.len() > FLYPATH_MESSAGE_MAX_LEN {
+                issues.push(CatalogIssue::MessageTooLong {
+                    theme: theme.cloned(),
+                    key: key.to_string(),
+                    message: message.clone(),
+                    len: message.len(),
+                });
+            } else if message.len() > FLYPATH_SINGLE_FRAGMENT_LEN {
+                issues.push(CatalogIssue::MultiFragment {
+                    theme: theme.cloned(),
+                    key: key.to_string(),
+                    message: message.clone(),
+                    len: message.len(),
+                });
+            }
+        }
     }
 
     /// Takes a `FlyPathModes` and an event or command (converted to a string using `Messages::drone_event_to_string` or `Messages::drone_command_to_string`)
     /// and retrieves a clone of the messages for the specified event/command and mode from the `Messages` struct.
+    /// If the event has a list for every language, the list of the fallback language of the catalog is used.
     ///
     /// # Returns
     /// - `Some(Vec<String>)`: A vector of messages for the specified event/command and mode.
@@ -69,20 +719,84 @@ impl Messages {
         mode: &FlyPathModes,
         event_or_command: &str,
     ) -> Option<Vec<String>> {
-        match mode {
-            FlyPathModes::Spicy(theme) => self
-                .spicy
-                .get(&theme.to_string())
-                .and_then(|events| events.get(event_or_command))
-                .filter(|messages| !messages.is_empty())
-                .cloned(),
-            FlyPathModes::BrainRot => self
-                .brainrot
-                .get(event_or_command)
-                .filter(|messages| !messages.is_empty())
-                .cloned(),
-            _ => None,
+        self.get_messages_for_language(mode, event_or_command, None)
+    }
+
+    /// Same as `get_messages_for_mode` but resolves the best variant for `language`, trying in order:
+    /// - the list of `language`, e.g. `"it-CH"`, then of its parent languages, e.g. `"it"`
+    /// - the list of the fallback language of the catalog, `"en"` by default
+    /// - the list without a language
+    ///
+    /// # Returns
+    /// - `None`: If none of them has messages.
+    pub fn get_messages_for_language(
+        &self,
+        mode: &FlyPathModes,
+        event_or_command: &str,
+        language: Option<&str>,
+    ) -> Option<Vec<String>> {
+        self.list(mode, event_or_command, language)
+            .map(|(_, list, _)| {
+                list.messages
+                    .iter()
+                    .map(|message| message.text.clone())
+                    .collect()
+            })
+    }
+
+    // The non-empty list of the mode for the event/command in `language`, with its theme and its strategy
+    fn list(
+        &self,
+        mode: &FlyPathModes,
+        event_or_command: &str,
+        language: Option<&str>,
+    ) -> Option<(Option<String>, &MessageList, SelectionStrategy)> {
+        let (theme, variants) = match mode {
+            FlyPathModes::Spicy(theme) => {
+                let theme = theme.to_string();
+                let variants = self.spicy.get(&theme)?.get(event_or_command)?;
+                (Some(theme), variants)
+            }
+            FlyPathModes::BrainRot => (None, self.brainrot.get(event_or_command)?),
+            _ => return None,
+        };
+        let list = variants.resolve(&self.language_chain(language))?;
+        let section = theme.as_deref().unwrap_or("brainrot");
+        let strategy = list
+            .strategy
+            .or_else(|| self.strategies.get(section).copied())
+            .unwrap_or_default();
+        Some((theme, list, strategy))
+    }
+
+    // `language` and its parent languages, e.g. `["it-CH", "it"]`, then the fallback language
+    fn language_chain<'a>(&'a self, language: Option<&'a str>) -> Vec<&'a str> {
+        let mut chain = Vec::new();
+        let mut language = language;
+        while let Some(tag) = language {
+            chain.push(tag);
+            language = tag.rsplit_once('-').map(|(parent, _)| parent);
         }
+        chain.push(
+            self.fallback_language
+                .as_deref()
+                .unwrap_or(DEFAULT_LANGUAGE),
+        );
+        chain
+    }
+
+    /// Every language with at least a list in the catalog, sorted
+    pub fn languages(&self) -> Vec<String> {
+        let mut languages: Vec<String> = self
+            .spicy
+            .values()
+            .flat_map(HashMap::values)
+            .chain(self.brainrot.values())
+            .flat_map(|variants| variants.languages.keys().cloned())
+            .collect();
+        languages.sort();
+        languages.dedup();
+        languages
     }
 
     /// Retrieves a random message for the given mode and event/command.
@@ -91,10 +805,48 @@ impl Messages {
     /// - `Some(String)`: A randomly selected message from the messages for that mode and event/command.
     /// - `None`: If no messages are found.
     pub fn get_rand_message(&self, mode: &FlyPathModes, event_or_command: &str) -> Option<String> {
-        self.get_messages_for_mode(mode, event_or_command)
-            .map(|messages| {
-                let rand_index = rand::thread_rng().gen_range(0..messages.len());
-                messages[rand_index].clone()
+        self.get_rand_message_with(mode, event_or_command, &mut rand::thread_rng())
+    }
+
+    /// Same as `get_rand_message` but the message is chosen using `rng`
+    pub fn get_rand_message_with<R: Rng + ?Sized>(
+        &self,
+        mode: &FlyPathModes,
+        event_or_command: &str,
+        rng: &mut R,
+    ) -> Option<String> {
+        self.list(mode, event_or_command, None)
+            .map(|(_, list, strategy)| {
+                let index = selection::pick_stateless(strategy, &list.messages, rng);
+                list.messages[index].text.clone()
+            })
+    }
+
+    /// Pick a message for the mode and the event/command following the `SelectionStrategy` of its list,
+    /// `selector` keeps the state of the round-robin and shuffle-bag lists between calls and chooses the
+    /// language, see `MessageSelector::with_language` and `get_messages_for_language`.
+    /// `get_rand_message_with` has no state: weighted lists still use their weights, shuffle-bag and
+    /// round-robin lists are picked uniformly.
+    ///
+    /// # Returns
+    /// - `None`: If no messages are found.
+    pub fn select_message<R: Rng + ?Sized>(
+        &self,
+        mode: &FlyPathModes,
+        event_or_command: &str,
+        selector: &mut MessageSelector,
+        rng: &mut R,
+    ) -> Option<String> {
+        self.list(mode, event_or_command, selector.language())
+            .map(|(theme, list, strategy)| {
+                let index = selector.pick(
+                    theme.as_deref(),
+                    event_or_command,
+                    strategy,
+                    &list.messages,
+                    rng,
+                );
+                list.messages[index].text.clone()
             })
     }
 
@@ -104,8 +856,8 @@ impl Messages {
     /// - `pack_type`: MsgFragment(Fragment{
     ///         - `fragment_index`: max value of u64,
     ///         - `total_n_framgents`: 0,
-    ///         - `length`: real len of message`s bytes,
-    ///         - `data`: message in bytes
+    ///         - `length`: real len of message`s bytes, plus 8,
+    ///         - `data`: magic prefix, checksum and message in bytes
     ///    })
     /// - `routing_header`: SourceRoutingHeader{
     ///         - `hop_index`: max number of possible hops,
@@ -116,46 +868,170 @@ impl Messages {
     /// # Returns
     /// - `Ok(None)`: Does not exist a message for that mode and that event/command
     /// - `Ok(Some(DroneEvent))`: DroneEvent is the event that can be sended to the controller
-    /// - `Ok(Err(String))`: the message is too and can not contained inside a `Fragment`, TOO LONG means that the number of UTF-2 bytes that encode the message is too long
+    /// - `Err(FlyPathError::MessageTooLong)`: the message, after filling its placeholders, is longer than
+    ///   `FLYPATH_SINGLE_FRAGMENT_LEN` (120 bytes) and can not be contained inside a single `Fragment`
     ///
+    /// The capacity is lower than the 128 bytes of a `Fragment` because of the magic prefix and the checksum,
+    /// and lower than the `FLYPATH_MESSAGE_MAX_LEN` accepted by `validate`, which reports the longer messages
+    /// with `CatalogIssue::MultiFragment`. Use `generate_droneEvents_to_controller` to send them.
     pub fn generate_droneEvent_to_controller(
         &self,
         mode: &FlyPathModes,
         event_or_command: &str,
         nodeId: NodeId,
-    ) -> Result<Option<DroneEvent>, String> {
-        if let Some(message) = self.get_rand_message(mode, event_or_command) {
-            let bytes = message.into_bytes();
-            if bytes.len() > FRAGMENT_DSIZE {
-                Err("Failed to generate a message: Too Long".to_string())
-            } else {
-                let fragment = Fragment {
-                    fragment_index: u64::MAX,
-                    total_n_fragments: 0,
-                    length: bytes.len() as u8,
-                    data: {
-                        let mut data = [0; FRAGMENT_DSIZE];
-                        data[..bytes.len()].copy_from_slice(&bytes);
-                        data
-                    },
-                };
+    ) -> Result<Option<DroneEvent>, FlyPathError> {
+        self.generate_droneEvent_to_controller_with(
+            mode,
+            event_or_command,
+            nodeId,
+            &mut rand::thread_rng(),
+        )
+    }
 
-                let packet = Packet {
-                    pack_type: PacketType::MsgFragment(fragment),
-                    routing_header: SourceRoutingHeader {
-                        hop_index: usize::MAX,
-                        hops: vec![nodeId], // with this che controller know the sender
-                    },
-                    session_id: u64::MAX,
-                };
+    /// Same as `generate_droneEvent_to_controller` but the message is chosen using `rng`
+    pub fn generate_droneEvent_to_controller_with<R: Rng + ?Sized>(
+        &self,
+        mode: &FlyPathModes,
+        event_or_command: &str,
+        nodeId: NodeId,
+        rng: &mut R,
+    ) -> Result<Option<DroneEvent>, FlyPathError> {
+        match self.get_rand_message_with(mode, event_or_command, rng) {
+            Some(message) => {
+                flypath_event(nodeId, &Self::render(&message, &node_values(nodeId))).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
 
-                Ok(Some(DroneEvent::PacketSent(packet)))
+    /// Same as `generate_droneEvent_to_controller` but the message has a header (see `FlyPathMessage`) and
+    /// a message longer than a `Fragment` is split across several events, see `FlyPathMessageExtractor`.
+    ///
+    /// # Returns
+    /// - `Ok(Vec<DroneEvent>)`: the events to send to the controller in order, empty if there is no message
+    /// - `Err(FlyPathError::MessageTooLong)`: the message is longer than `FLYPATH_MESSAGE_MAX_LEN` bytes
+    pub fn generate_droneEvents_to_controller(
+        &self,
+        mode: &FlyPathModes,
+        event_or_command: &str,
+        nodeId: NodeId,
+    ) -> Result<Vec<DroneEvent>, FlyPathError> {
+        self.generate_droneEvents_to_controller_with(
+            mode,
+            event_or_command,
+            nodeId,
+            &mut rand::thread_rng(),
+        )
+    }
+
+    /// Same as `generate_droneEvents_to_controller` but the message is chosen using `rng`
+    pub fn generate_droneEvents_to_controller_with<R: Rng + ?Sized>(
+        &self,
+        mode: &FlyPathModes,
+        event_or_command: &str,
+        nodeId: NodeId,
+        rng: &mut R,
+    ) -> Result<Vec<DroneEvent>, FlyPathError> {
+        let values = node_values(nodeId);
+        match self.generate_flypath_message_with(mode, event_or_command, nodeId, &values, rng) {
+            Some(message) => message.to_events(),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Pick a message for the mode and the event/command using `rng` and fill its placeholders with
+    /// `values`, `{node_id}` is `nodeId` if `values` does not have it. The header is filled from the mode,
+    /// the key and the session of `values`; the sequence number is `0`, the drone sets it.
+    ///
+    /// # Returns
+    /// - `None`: Does not exist a message for that mode and that event/command
+    pub fn generate_flypath_message_with<R: Rng + ?Sized>(
+        &self,
+        mode: &FlyPathModes,
+        event_or_command: &str,
+        nodeId: NodeId,
+        values: &TemplateValues,
+        rng: &mut R,
+    ) -> Option<FlyPathMessage> {
+        let template = self.get_rand_message_with(mode, event_or_command, rng)?;
+        Some(Self::flypath_message(
+            mode,
+            event_or_command,
+            nodeId,
+            &template,
+            values,
+        ))
+    }
+
+    /// Same as `generate_flypath_message_with` but the message is picked with `select_message`
+    pub fn select_flypath_message<R: Rng + ?Sized>(
+        &self,
+        mode: &FlyPathModes,
+        event_or_command: &str,
+        nodeId: NodeId,
+        values: &TemplateValues,
+        selector: &mut MessageSelector,
+        rng: &mut R,
+    ) -> Option<FlyPathMessage> {
+        let template = self.select_message(mode, event_or_command, selector, rng)?;
+        Some(Self::flypath_message(
+            mode,
+            event_or_command,
+            nodeId,
+            &template,
+            values,
+        ))
+    }
+
+    /// Fill the placeholders of `template` and the header of the message, as the drone `nodeId` does when
+    /// it sends `template` for `event_or_command` in `mode`. `FlyPathMessage::to_events` tells how many
+    /// fragments the message needs.
+    pub fn flypath_message(
+        mode: &FlyPathModes,
+        event_or_command: &str,
+        nodeId: NodeId,
+        template: &str,
+        values: &TemplateValues,
+    ) -> FlyPathMessage {
+        let values = TemplateValues {
+            node_id: values.node_id.or(Some(nodeId)),
+            ..values.clone()
+        };
+        let text = Self::render(template, &values);
+        let (kind, theme) = match mode {
+            FlyPathModes::Default => (FlyPathMessageKind::Text, None),
+            FlyPathModes::Spicy(theme) if Self::is_command_key(event_or_command) => {
+                (FlyPathMessageKind::Command, Some(theme.to_string()))
             }
-        } else {
-            Ok(None)
+            FlyPathModes::Spicy(theme) => (FlyPathMessageKind::Event, Some(theme.to_string())),
+            FlyPathModes::BrainRot => (FlyPathMessageKind::BrainRot, None),
+        };
+        FlyPathMessage {
+            node_id: nodeId,
+            kind,
+            trigger: event_or_command.to_string(),
+            theme,
+            seq: 0,
+            session_id: values.session_id,
+            text,
         }
     }
 
+    /// Replace the placeholders of `message`, e.g. `{node_id}`, with `values`, see `TemplateValues`.
+    /// The length of a message is checked after the placeholders are replaced.
+    pub fn render(message: &str, values: &TemplateValues) -> String {
+        template::render(message, values)
+    }
+
+    /// `true` if `key` is a `DroneCommand` or a refused BrainRot command, e.g. `"Crash"` or `"NoCrash"`
+    pub fn is_command_key(key: &str) -> bool {
+        let command = key.strip_prefix("No").unwrap_or(key);
+        matches!(
+            command,
+            "AddSender" | "RemoveSender" | "SetPacketDropRate" | "Crash"
+        )
+    }
+
     /// Convert the DroneEvent to String, necessary for lookup the messages
     pub fn drone_event_to_string(event: &DroneEvent) -> &str {
         match event {
@@ -176,50 +1052,301 @@ impl Messages {
     }
 }
 
-/// Identifies and extracts a FlyPath message from a given `DroneEvent`.
-///
-/// # Returns
-/// - `Some((NodeId, String))`:
-///   - `NodeId`: The identifier of the FlyPath drone operating in spicy mode.
-///   - `String`: The themed message associated with the event.
-/// - `None`: If the event is a normal `DroneEvent` or invalid as a FlyPath event.
-///
-/// # Examples
-/// ```rust
-/// // Assuming `event` is a valid FlyPath DroneEvent:
-/// use flyPath::extract_flypath_message;
-/// if let Some((node_id, message)) = extract_flypath_message(&event) {
-///     println!("FlyPath Event Detected! Node: {}, Message: {}", node_id, message);
-/// } else {
-///     println!("This is not a FlyPath event.");
-/// }
-/// ```
-pub fn extract_flypath_message(event: &DroneEvent) -> Option<(NodeId, String)> {
-    if let DroneEvent::PacketSent(packet) = event {
-        if let PacketType::MsgFragment(fragment) = &packet.pack_type {
-            if packet.session_id == u64::MAX
-                && packet.routing_header.hop_index == usize::MAX
-                && !packet.routing_header.hops.is_empty()
-                && packet.routing_header.hops.len() == 1
-                && fragment.fragment_index == u64::MAX
-                && fragment.total_n_fragments == 0
-            {
-                // We can assume for sure that this special invalid Fragment is FlyPath Fragment
-                let node_id = packet.routing_header.hops.first().unwrap();
-                return Some((
-                    *node_id,
-                    String::from_utf8_lossy(&fragment.data[..fragment.length as usize]).to_string(),
-                ));
+// Values of a message sent outside a drone, only `{node_id}` is known
+fn node_values(node_id: NodeId) -> TemplateValues {
+    TemplateValues {
+        node_id: Some(node_id),
+        ..TemplateValues::default()
+    }
+}
+
+/// The result of `Messages::load_from_dir`
+#[derive(Debug)]
+pub struct ThemePacks {
+    /// the catalog of the files without errors
+    pub messages: Messages,
+    /// the path of every skipped file and its error
+    pub errors: Vec<(String, FlyPathError)>,
+}
+
+/// A message of the catalog with the layer that supplied it, see `Messages::lines`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CatalogLine {
+    /// theme of the message, `None` for the brainrot section
+    pub theme: Option<String>,
+    /// event, command or BrainRot action of the message
+    pub key: String,
+    /// language of the list, `None` for the list without a language
+    pub language: Option<String>,
+    pub text: String,
+    /// layer that supplied the message: the path of its file, `"default"` for the embedded catalog,
+    /// `None` if it was parsed from a string
+    pub source: Option<String>,
+}
+
+/// A problem found by `Messages::validate`
+#[derive(Debug, Clone, PartialEq)]
+pub enum CatalogIssue {
+    /// A `FlyPathThemes` has no messages in the catalog, so that theme never speaks (warning)
+    MissingTheme(String),
+    /// The catalog contains a theme that is not built-in, it is selected with `FlyPathThemes::Custom` (warning)
+    CustomTheme(String),
+    /// A theme contains a key that is not an event or a command
+    UnknownKey { theme: String, key: String },
+    /// The brainrot section contains a key that is not a BrainRot action
+    UnknownBrainRotKey(String),
+    /// A list of messages is empty, `theme` is `None` for the brainrot section (warning)
+    EmptyList { theme: Option<String>, key: String },
+    /// A message is longer than `FLYPATH_MESSAGE_MAX_LEN` bytes and can not be sent even split across several `Fragment`s
+    MessageTooLong {
+        theme: Option<String>,
+        key: String,
+        message: String,
+        len: usize,
+    },
+    /// A message is longer than `FLYPATH_SINGLE_FRAGMENT_LEN` bytes: the drones split it across several
+    /// `Fragment`s and `generate_droneEvent_to_controller` fails with `MessageTooLong` (warning)
+    MultiFragment {
+        theme: Option<String>,
+        key: String,
+        message: String,
+        len: usize,
+    },
+    /// A theme pack of `Messages::load_from_dir` contains a theme already loaded from the file at `path`
+    DuplicateTheme { theme: String, path: String },
+    /// Every message of a list has weight `0`, so a weighted list can not pick any of them
+    ZeroWeights { theme: Option<String>, key: String },
+    /// The `strategies` section names a theme that is not in the catalog
+    UnknownStrategySection(String),
+    /// A message has an unknown placeholder or a brace without its pair, see `TemplateValues`
+    InvalidTemplate {
+        theme: Option<String>,
+        key: String,
+        message: String,
+        reason: String,
+    },
+}
+
+impl CatalogIssue {
+    /// `true` if the issue makes the catalog unusable, `false` if it is only a warning
+    pub fn is_error(&self) -> bool {
+        !matches!(
+            self,
+            CatalogIssue::MissingTheme(_)
+                | CatalogIssue::CustomTheme(_)
+                | CatalogIssue::EmptyList { .. }
+                | CatalogIssue::MultiFragment { .. }
+        )
+    }
+}
+
+impl fmt::Display for CatalogIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let section = |theme: &Option<String>| match theme {
+            Some(theme) => format!("theme '{}'", theme),
+            None => "brainrot".to_string(),
+        };
+        match self {
+            CatalogIssue::MissingTheme(theme) => write!(f, "theme '{}' is missing", theme),
+            CatalogIssue::CustomTheme(theme) => {
+                write!(f, "theme '{}' is not a built-in theme", theme)
+            }
+            CatalogIssue::UnknownKey { theme, key } => {
+                write!(f, "theme '{}' has unknown key '{}'", theme, key)
+            }
+            CatalogIssue::UnknownBrainRotKey(key) => {
+                write!(f, "brainrot has unknown key '{}'", key)
             }
+            CatalogIssue::EmptyList { theme, key } => {
+                write!(f, "{} has no messages for '{}'", section(theme), key)
+            }
+            CatalogIssue::ZeroWeights { theme, key } => {
+                write!(
+                    f,
+                    "{} has only messages of weight 0 for '{}'",
+                    section(theme),
+                    key
+                )
+            }
+            CatalogIssue::DuplicateTheme { theme, path } => {
+                write!(f, "theme '{}' is already loaded from {}", theme, path)
+            }
+            CatalogIssue::UnknownStrategySection(section) => {
+                write!(f, "strategies has unknown theme '{}'", section)
+            }
+            CatalogIssue::MessageTooLong {
+                theme, key, len, ..
+            } => write!(
+                f,
+                "{} has a message for '{}' of {} bytes (max {})",
+                section(theme),
+                key,
+                len,
+                FLYPATH_MESSAGE_MAX_LEN
+            ),
+            CatalogIssue::MultiFragment {
+                theme, key, len, ..
+            } => write!(
+                f,
+                "{} has a message for '{}' of {} bytes, split across several fragments (max {} in one)",
+                section(theme),
+                key,
+                len,
+                FLYPATH_SINGLE_FRAGMENT_LEN
+            ),
+            CatalogIssue::InvalidTemplate {
+                theme,
+                key,
+                message,
+                reason,
+            } => write!(
+                f,
+                "{} has an invalid message for '{}': {} in \"{}\"",
+                section(theme),
+                key,
+                reason,
+                message
+            ),
         }
     }
-    None // Return None if event is not a FlyPath DroneEvent
+}
+
+/// Result of `Messages::validate`, contains all the issues found in the catalog
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CatalogReport {
+    pub issues: Vec<CatalogIssue>,
+}
+
+impl CatalogReport {
+    /// `true` if the report does not contain errors, warnings are allowed
+    pub fn is_valid(&self) -> bool {
+        self.errors().next().is_none()
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &CatalogIssue> {
+        self.issues.iter().filter(|issue| issue.is_error())
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &CatalogIssue> {
+        self.issues.iter().filter(|issue| !issue.is_error())
+    }
+}
+
+impl fmt::Display for CatalogReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let issues: Vec<String> = self.issues.iter().map(|issue| issue.to_string()).collect();
+        write!(f, "{}", issues.join("; "))
+    }
+}
+
+/// The catalog in the same format read by `from_json_str`, with the themes, the keys and the languages
+/// sorted, e.g. for `serde_json::to_string_pretty` or `toml::to_string`. The sources of the messages are
+/// not written.
+impl Serialize for Messages {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut catalog = Map::new();
+        catalog.insert("brainrot".to_string(), self.section_value(&self.brainrot));
+        if let Some(language) = &self.fallback_language {
+            catalog.insert("fallback_language".to_string(), json!(language));
+        }
+        let spicy: BTreeMap<String, Value> = self
+            .spicy
+            .iter()
+            .map(|(theme, keys)| (theme.clone(), self.section_value(keys)))
+            .collect();
+        catalog.insert("spicy".to_string(), sorted(spicy));
+        if !self.strategies.is_empty() {
+            let strategies: BTreeMap<String, Value> = self
+                .strategies
+                .iter()
+                .map(|(section, strategy)| (section.clone(), json!(strategy)))
+                .collect();
+            catalog.insert("strategies".to_string(), sorted(strategies));
+        }
+        Value::Object(catalog).serialize(serializer)
+    }
+}
+
+impl Messages {
+    // The lists of a theme or of the brainrot section with their keys sorted
+    fn section_value(&self, lists: &HashMap<String, MessageVariants>) -> Value {
+        sorted(
+            lists
+                .iter()
+                .map(|(key, variants)| (key.clone(), self.variants_value(variants)))
+                .collect(),
+        )
+    }
+
+    // A list, or an object with the list of every language. After `layer` an event can have both,
+    // then the list without a language is written for the fallback language if it has no variant
+    fn variants_value(&self, variants: &MessageVariants) -> Value {
+        if let (Some(list), true) = (&variants.list, variants.languages.is_empty()) {
+            return list_value(list);
+        }
+        let mut languages: BTreeMap<String, Value> = variants
+            .languages
+            .iter()
+            .map(|(language, list)| (language.clone(), list_value(list)))
+            .collect();
+        if let Some(list) = &variants.list {
+            let fallback = self
+                .fallback_language
+                .as_deref()
+                .unwrap_or(DEFAULT_LANGUAGE);
+            languages
+                .entry(fallback.to_string())
+                .or_insert_with(|| list_value(list));
+        }
+        sorted(languages)
+    }
+}
+
+// An array of messages, or an object with the options of the list and its messages
+fn list_value(list: &MessageList) -> Value {
+    let messages: Vec<Value> = list
+        .messages
+        .iter()
+        .map(|message| match message.weight {
+            1 => json!(message.text),
+            weight => json!({ "text": message.text, "weight": weight }),
+        })
+        .collect();
+    if list.strategy.is_none() && list.merge == MergeMode::Replace {
+        return Value::Array(messages);
+    }
+    let mut options = Map::new();
+    if list.merge == MergeMode::Append {
+        options.insert("merge".to_string(), json!(list.merge));
+    }
+    options.insert("messages".to_string(), Value::Array(messages));
+    if let Some(strategy) = list.strategy {
+        options.insert("strategy".to_string(), json!(strategy));
+    }
+    Value::Object(options)
+}
+
+// A JSON object with the entries inserted in the order of their keys
+fn sorted(entries: BTreeMap<String, Value>) -> Value {
+    Value::Object(entries.into_iter().collect())
+}
+
+/// The default `Messages` is the stock catalog embedded at compile time from `resources/messages.json`.
+impl Default for Messages {
+    fn default() -> Self {
+        Self::from_json_str(DEFAULT_CATALOG)
+            .expect("The embedded message catalog is not valid")
+            .with_source("default")
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::flypath::FlyPathThemes;
+    use crate::flypath_message::extract_flypath_message;
+    use wg_2024::network::SourceRoutingHeader;
+    use wg_2024::packet::{Fragment, Packet, PacketType};
 
     #[test]
     fn test_valid_file() {
@@ -249,13 +1376,554 @@ mod tests {
         fs::remove_file(file_path).unwrap();
     }
 
+    #[test]
+    fn test_default_catalog() {
+        let messages = Messages::default();
+        let mode = FlyPathModes::Spicy(FlyPathThemes::Batman);
+        let event_or_command = Messages::drone_command_to_string(&DroneCommand::Crash);
+        assert!(messages
+            .get_messages_for_mode(&mode, event_or_command)
+            .is_some());
+        assert!(messages
+            .get_messages_for_mode(&FlyPathModes::BrainRot, event_or_command)
+            .is_some());
+    }
+
+    #[test]
+    fn test_custom_themes() {
+        let json_data = r#"
+        {
+            "spicy": {
+                "Star Wars": { "Crash": ["I have a bad feeling about this"] },
+                "Dark Souls": { "Crash": ["You died"] }
+            },
+            "brainrot": {}
+        }
+        "#;
+        let messages = Messages::from_json_str(json_data).unwrap();
+        let star_wars = FlyPathThemes::Custom("Star Wars".to_string());
+        assert_eq!(
+            messages.themes(),
+            vec![FlyPathThemes::DarkSouls, star_wars.clone()]
+        );
+        assert_eq!(messages.theme("Star Wars").unwrap(), star_wars);
+        assert_eq!(
+            messages.theme("DarkSouls").unwrap(),
+            FlyPathThemes::DarkSouls
+        );
+        assert!(matches!(
+            messages.theme("Pingu"),
+            Err(FlyPathError::UnknownTheme(name)) if name == "Pingu"
+        ));
+
+        let report = messages.validate();
+        assert!(report.is_valid());
+        assert!(report
+            .warnings()
+            .any(|issue| *issue == CatalogIssue::CustomTheme("Star Wars".to_string())));
+
+        let message = messages
+            .generate_flypath_message_with(
+                &FlyPathModes::Spicy(star_wars),
+                "Crash",
+                1,
+                &TemplateValues::default(),
+                &mut rand::thread_rng(),
+            )
+            .unwrap();
+        assert_eq!(message.text, "I have a bad feeling about this");
+        assert_eq!(message.theme.as_deref(), Some("Star Wars"));
+    }
+
+    #[test]
+    fn test_selection_strategies() {
+        let json_data = r#"
+        {
+            "spicy": {
+                "Batman": {
+                    "Crash": ["One", {"text": "Two", "weight": 0}, {"text": "Three"}],
+                    "RemoveSender": {"strategy": "uniform", "messages": ["A", "B"]}
+                },
+                "Pingu": { "Crash": [{"text": "Noot", "weight": 0}] }
+            },
+            "brainrot": {
+                "Crash": {"strategy": "round-robin", "messages": ["1", "2", "3"]}
+            },
+            "strategies": { "Batman": "round-robin", "Rocket": "shuffle-bag" }
+        }
+        "#;
+        let messages = Messages::from_json_str(json_data).unwrap();
+        let batman = FlyPathModes::Spicy(FlyPathThemes::Batman);
+        assert_eq!(
+            messages.get_messages_for_mode(&batman, "Crash"),
+            Some(vec![
+                "One".to_string(),
+                "Two".to_string(),
+                "Three".to_string()
+            ])
+        );
+
+        // the strategy of the theme, the weights are ignored
+        let mut rng = rand::thread_rng();
+        let mut selector = MessageSelector::new();
+        let picked: Vec<String> = (0..4)
+            .filter_map(|_| messages.select_message(&batman, "Crash", &mut selector, &mut rng))
+            .collect();
+        assert_eq!(picked, vec!["One", "Two", "Three", "One"]);
+        let picked: Vec<String> = (0..4)
+            .filter_map(|_| {
+                messages.select_message(&FlyPathModes::BrainRot, "Crash", &mut selector, &mut rng)
+            })
+            .collect();
+        assert_eq!(picked, vec!["1", "2", "3", "1"]);
+
+        let report = messages.validate();
+        assert!(report.errors().any(|issue| *issue
+            == CatalogIssue::ZeroWeights {
+                theme: Some("Pingu".to_string()),
+                key: "Crash".to_string()
+            }));
+        assert!(report
+            .errors()
+            .any(|issue| *issue == CatalogIssue::UnknownStrategySection("Rocket".to_string())));
+        assert_eq!(report.errors().count(), 2);
+
+        // the default strategy uses the weights
+        let weighted = Messages::from_json_str(
+            r#"{"spicy": {"Batman": {"Crash": ["One", {"text": "Two", "weight": 0}]}}, "brainrot": {}}"#,
+        )
+        .unwrap();
+        for _ in 0..50 {
+            assert_eq!(
+                weighted.get_rand_message(&batman, "Crash").as_deref(),
+                Some("One")
+            );
+        }
+    }
+
+    #[test]
+    fn test_languages() {
+        let json_data = r#"
+        {
+            "spicy": {
+                "Batman": {
+                    "Crash": {"en": ["I'm Batman"], "it": ["Sono Batman"], "it-CH": []},
+                    "RemoveSender": ["Where are they?"],
+                    "AddSender": {"it": ["Un nuovo amico"]}
+                }
+            },
+            "brainrot": {
+                "Crash": {"de": ["Tschüss"], "fr": {"strategy": "round-robin", "messages": []}}
+            }
+        }
+        "#;
+        let messages = Messages::from_json_str(json_data).unwrap();
+        let batman = FlyPathModes::Spicy(FlyPathThemes::Batman);
+        let get = |key: &str, language: Option<&str>| {
+            messages
+                .get_messages_for_language(&batman, key, language)
+                .map(|messages| messages.join(""))
+        };
+        assert_eq!(get("Crash", Some("it")).as_deref(), Some("Sono Batman"));
+        // an empty variant falls back to its parent language
+        assert_eq!(get("Crash", Some("it-CH")).as_deref(), Some("Sono Batman"));
+        assert_eq!(get("Crash", Some("fr")).as_deref(), Some("I'm Batman"));
+        assert_eq!(get("Crash", None).as_deref(), Some("I'm Batman"));
+        assert_eq!(
+            get("RemoveSender", Some("it")).as_deref(),
+            Some("Where are they?")
+        );
+        assert_eq!(
+            get("AddSender", Some("it")).as_deref(),
+            Some("Un nuovo amico")
+        );
+        assert_eq!(get("AddSender", Some("fr")), None);
+        assert_eq!(
+            messages.get_messages_for_mode(&batman, "Crash"),
+            Some(vec!["I'm Batman".to_string()])
+        );
+
+        let mut selector = MessageSelector::with_language("it");
+        assert_eq!(
+            messages
+                .select_message(&batman, "Crash", &mut selector, &mut rand::thread_rng())
+                .as_deref(),
+            Some("Sono Batman")
+        );
+        assert_eq!(messages.languages(), vec!["de", "en", "fr", "it", "it-CH"]);
+
+        let report = messages.validate();
+        assert!(report.warnings().any(|issue| *issue
+            == CatalogIssue::EmptyList {
+                theme: Some("Batman".to_string()),
+                key: "Crash (it-CH)".to_string()
+            }));
+        assert!(report.warnings().any(|issue| *issue
+            == CatalogIssue::EmptyList {
+                theme: None,
+                key: "Crash (fr)".to_string()
+            }));
+        assert!(report.is_valid());
+
+        let italian = Messages::from_json_str(
+            r#"{"spicy": {"Batman": {"Crash": {"it": ["Sono Batman"]}}}, "brainrot": {}, "fallback_language": "it"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            italian.get_messages_for_mode(&batman, "Crash"),
+            Some(vec!["Sono Batman".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_toml() {
+        let toml_data = r#"
+            fallback_language = "it"
+
+            [spicy."Dark Souls"]
+            Crash = ["You died"]
+            RemoveSender = { strategy = "round-robin", messages = ["Praise the sun", { text = "Git gud", weight = 2 }] }
+
+            [spicy."Dark Souls".AddSender]
+            it = ["Un nuovo falò"]
+
+            [brainrot]
+            Crash = ["skibidi"]
+        "#;
+        let messages = Messages::from_toml_str(toml_data).unwrap();
+        assert!(messages.validate().is_valid());
+        let dark_souls = FlyPathModes::Spicy(FlyPathThemes::DarkSouls);
+        assert_eq!(
+            messages.get_messages_for_mode(&dark_souls, "RemoveSender"),
+            Some(vec!["Praise the sun".to_string(), "Git gud".to_string()])
+        );
+        assert_eq!(
+            messages.get_messages_for_mode(&dark_souls, "AddSender"),
+            Some(vec!["Un nuovo falò".to_string()])
+        );
+        let result = Messages::from_toml_str("[spicy.Batman]\nCrash = 3");
+        assert!(matches!(result, Err(FlyPathError::Parse(_))));
+        assert!(!result.unwrap_err().to_string().contains("JSON"));
+    }
+
+    #[test]
+    fn test_layers() {
+        let team_path =
+            std::env::temp_dir().join(format!("flypath_team_{}.toml", std::process::id()));
+        let drone_path =
+            std::env::temp_dir().join(format!("flypath_drone_{}.json", std::process::id()));
+        let team_path = team_path.to_str().unwrap();
+        let drone_path = drone_path.to_str().unwrap();
+        fs::write(
+            team_path,
+            r#"
+            [spicy.Batman]
+            Crash = ["Team crash"]
+            RemoveSender = { merge = "append", messages = ["Team bye"] }
+
+            [spicy."Star Wars"]
+            Crash = ["It's a trap"]
+            "#,
+        )
+        .unwrap();
+        fs::write(
+            drone_path,
+            r#"{"spicy": {"Batman": {"Crash": {"merge": "append", "messages": ["Drone crash"]}}}}"#,
+        )
+        .unwrap();
+
+        let messages = Messages::load_layers(&[team_path, drone_path]).unwrap();
+        let _ = fs::remove_file(team_path);
+        let _ = fs::remove_file(drone_path);
+        assert!(messages.validate().is_valid());
+
+        let batman = FlyPathModes::Spicy(FlyPathThemes::Batman);
+        // replaced by the team, then appended by the drone
+        assert_eq!(
+            messages.get_messages_for_mode(&batman, "Crash"),
+            Some(vec!["Team crash".to_string(), "Drone crash".to_string()])
+        );
+        let defaults = Messages::default();
+        let default_lines = defaults
+            .get_messages_for_mode(&batman, "RemoveSender")
+            .unwrap();
+        let remove_sender = messages
+            .get_messages_for_mode(&batman, "RemoveSender")
+            .unwrap();
+        assert_eq!(remove_sender.len(), default_lines.len() + 1);
+        assert_eq!(remove_sender.last().map(String::as_str), Some("Team bye"));
+        // lists missing from the layers are kept
+        assert_eq!(
+            messages.get_messages_for_mode(&batman, "PacketSent"),
+            defaults.get_messages_for_mode(&batman, "PacketSent")
+        );
+        assert!(messages
+            .themes()
+            .contains(&FlyPathThemes::Custom("Star Wars".to_string())));
+
+        let source = |key: &str, text: &str| {
+            messages
+                .lines()
+                .into_iter()
+                .find(|line| {
+                    line.theme.as_deref() == Some("Batman") && line.key == key && line.text == text
+                })
+                .and_then(|line| line.source)
+        };
+        assert_eq!(source("Crash", "Team crash").as_deref(), Some(team_path));
+        assert_eq!(source("Crash", "Drone crash").as_deref(), Some(drone_path));
+        assert_eq!(
+            source("RemoveSender", &default_lines[0]).as_deref(),
+            Some("default")
+        );
+        assert!(matches!(
+            Messages::load_layers(&["non_existent.toml"]),
+            Err(FlyPathError::Io { .. })
+        ));
+    }
+
+    #[test]
+    fn test_load_from_dir() {
+        let dir = std::env::temp_dir().join(format!("flypath_packs_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let files = [
+            ("Batman.json", r#"{"Crash": ["I'm Batman"]}"#),
+            ("DarkSouls.json", r#"{"Crash": ["You died"]}"#),
+            ("Star Wars.toml", r#"Crash = ["It's a trap"]"#),
+            ("brainrot.json", r#"{"Crash": ["skibidi"]}"#),
+            ("Broken.json", r#"{"Crash": "#),
+            ("Pingu.json", r#"{"Jump": ["Noot"]}"#),
+            ("batman.toml", r#"Crash = ["I'm not Batman"]"#),
+            ("notes.txt", "not a theme"),
+        ];
+        for (name, content) in files {
+            fs::write(dir.join(name), content).unwrap();
+        }
+
+        let packs = Messages::load_from_dir(dir.to_str().unwrap()).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+        assert_eq!(
+            packs.messages.themes(),
+            vec![
+                FlyPathThemes::Batman,
+                FlyPathThemes::DarkSouls,
+                FlyPathThemes::Custom("Star Wars".to_string())
+            ]
+        );
+        assert_eq!(
+            packs
+                .messages
+                .get_messages_for_mode(&FlyPathModes::BrainRot, "Crash"),
+            Some(vec!["skibidi".to_string()])
+        );
+        assert!(packs.messages.validate().is_valid());
+        assert!(packs.messages.lines().iter().all(|line| {
+            line.source.as_deref().is_some_and(|source| {
+                source.ends_with(".json") || source.ends_with("Star Wars.toml")
+            })
+        }));
+
+        assert_eq!(packs.errors.len(), 3);
+        assert!(packs.errors[0].0.ends_with("Broken.json"));
+        assert!(matches!(packs.errors[0].1, FlyPathError::Parse(_)));
+        assert!(packs.errors[1].0.ends_with("Pingu.json"));
+        assert!(matches!(packs.errors[1].1, FlyPathError::InvalidCatalog(_)));
+        // the second file of a theme is not merged silently
+        assert!(packs.errors[2].0.ends_with("batman.toml"));
+        assert!(matches!(
+            &packs.errors[2].1,
+            FlyPathError::InvalidCatalog(report) if matches!(
+                &report.issues[..],
+                [CatalogIssue::DuplicateTheme { theme, path }]
+                    if theme == "batman" && path.ends_with("Batman.json")
+            )
+        ));
+        assert_eq!(
+            packs
+                .messages
+                .get_messages_for_mode(&FlyPathModes::Spicy(FlyPathThemes::Batman), "Crash"),
+            Some(vec!["I'm Batman".to_string()])
+        );
+
+        assert!(matches!(
+            Messages::load_from_dir("non_existent_dir"),
+            Err(FlyPathError::Io { .. })
+        ));
+    }
+
+    #[test]
+    fn test_serialize() {
+        let json_data = r#"{
+            "spicy": {
+                "Pingu": {
+                    "RemoveSender": {"en": ["Bye"], "it": ["Ciao"]},
+                    "Crash": {"strategy": "shuffle-bag", "messages": ["Noot", {"text": "NOOT", "weight": 3}]}
+                },
+                "Batman": {"Crash": ["I'm Batman"]}
+            },
+            "brainrot": {"Crash": {"merge": "append", "messages": ["skibidi"]}},
+            "strategies": {"Pingu": "round-robin"},
+            "fallback_language": "it"
+        }"#;
+        let messages = Messages::from_json_str(json_data).unwrap();
+        let json = serde_json::to_string(&messages).unwrap();
+        assert_eq!(
+            json,
+            concat!(
+                r#"{"brainrot":{"Crash":{"merge":"append","messages":["skibidi"]}},"fallback_language":"it","#,
+                r#""spicy":{"Batman":{"Crash":["I'm Batman"]},"Pingu":{"Crash":{"messages":["Noot","#,
+                r#"{"text":"NOOT","weight":3}],"strategy":"shuffle-bag"},"RemoveSender":{"en":["Bye"],"it":["Ciao"]}}},"#,
+                r#""strategies":{"Pingu":"round-robin"}}"#
+            )
+        );
+        let again = Messages::from_json_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&again).unwrap(), json);
+
+        let toml = toml::to_string(&messages).unwrap();
+        let from_toml = Messages::from_toml_str(&toml).unwrap();
+        assert_eq!(serde_json::to_string(&from_toml).unwrap(), json);
+
+        // the list without a language of a layered catalog is written for the fallback language
+        let layered = messages.layer(
+            Messages::from_json_str(
+                r#"{"spicy": {"Batman": {"Crash": {"fr": ["Je suis Batman"]}}}}"#,
+            )
+            .unwrap(),
+        );
+        let json = serde_json::to_value(&layered).unwrap();
+        assert_eq!(
+            json["spicy"]["Batman"]["Crash"],
+            serde_json::json!({"fr": ["Je suis Batman"], "it": ["I'm Batman"]})
+        );
+    }
+
+    #[test]
+    fn test_templates() {
+        let padding = "x".repeat(110);
+        let json_data = r#"
+        {
+            "spicy": {
+                "Batman": {
+                    "RemoveSender": ["PADDING {node_id} {neighbor}"],
+                    "Crash": ["Broken {node"]
+                }
+            },
+            "brainrot": {}
+        }
+        "#
+        .replace("PADDING", &padding);
+        let messages = Messages::from_json_str(&json_data).unwrap();
+        let report = messages.validate();
+        assert_eq!(report.errors().count(), 1);
+        assert!(report.errors().any(|issue| matches!(
+            issue,
+            CatalogIssue::InvalidTemplate { key, .. } if key == "Crash"
+        )));
+
+        // the length is checked after the placeholders are replaced
+        let mode = FlyPathModes::Spicy(FlyPathThemes::Batman);
+        let event = messages
+            .generate_droneEvent_to_controller(&mode, "RemoveSender", 1)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            extract_flypath_message(&event),
+            Some((1, format!("{} 1 ?", padding)))
+        );
+
+        let values = TemplateValues::from_command(&DroneCommand::RemoveSender(4));
+        let message = messages
+            .generate_flypath_message_with(
+                &mode,
+                "RemoveSender",
+                1,
+                &values,
+                &mut rand::thread_rng(),
+            )
+            .unwrap();
+        assert_eq!(message.text, format!("{} 1 4", padding));
+    }
+
+    #[test]
+    fn test_validate() {
+        // Case: default catalog has only warnings
+        {
+            let report = Messages::default().validate();
+            assert!(report.is_valid(), "Unexpected errors: {}", report);
+        }
+
+        // Case: every kind of issue
+        {
+            let json_data = r#"
+            {
+                "spicy": {
+                    "DarkSoul": {
+                        "Crash": ["You died"],
+                        "Jump": ["Nope"],
+                        "PacketSent": [],
+                        "PacketDropped": ["MultiFragment"]
+                    }
+                },
+                "brainrot": {
+                    "Dance": ["skibidi"],
+                    "PacketSentPANIC": ["TooLong"]
+                }
+            }
+            "#
+            .replace("TooLong", &"Fill".repeat(FLYPATH_MESSAGE_MAX_LEN / 4 + 1))
+            .replace(
+                "MultiFragment",
+                &"Fill".repeat(FLYPATH_SINGLE_FRAGMENT_LEN / 4 + 1),
+            );
+            let messages = Messages::from_json_str(&json_data).unwrap();
+            let report = messages.validate();
+            assert!(!report.is_valid());
+
+            for theme in FlyPathThemes::ALL.iter() {
+                assert!(report
+                    .issues
+                    .contains(&CatalogIssue::MissingTheme(theme.to_string())));
+            }
+            assert!(report
+                .issues
+                .contains(&CatalogIssue::CustomTheme("DarkSoul".to_string())));
+            assert!(report.issues.contains(&CatalogIssue::UnknownKey {
+                theme: "DarkSoul".to_string(),
+                key: "Jump".to_string()
+            }));
+            assert!(report.issues.contains(&CatalogIssue::EmptyList {
+                theme: Some("DarkSoul".to_string()),
+                key: "PacketSent".to_string()
+            }));
+            assert!(report
+                .issues
+                .contains(&CatalogIssue::UnknownBrainRotKey("Dance".to_string())));
+            assert!(report.errors().any(|issue| matches!(
+                issue,
+                CatalogIssue::MessageTooLong {
+                    theme: None,
+                    len: 2052,
+                    ..
+                }
+            )));
+            // valid for the drones, but too long for `generate_droneEvent_to_controller`
+            assert!(report.warnings().any(|issue| matches!(
+                issue,
+                CatalogIssue::MultiFragment {
+                    theme: Some(_),
+                    len: 124,
+                    ..
+                }
+            )));
+            assert_eq!(report.warnings().count(), FlyPathThemes::ALL.len() + 3);
+        }
+    }
+
     #[test]
     fn test_file_not_found() {
         let result = Messages::load_from_file("non_existent.json");
         assert!(result.is_err());
-        assert!(result
-            .unwrap_err()
-            .contains("Failed to read the messages file"));
+        assert!(matches!(result, Err(FlyPathError::Io { .. })));
     }
 
     #[test]
@@ -266,9 +1934,7 @@ mod tests {
 
         let result = Messages::load_from_file(file_path);
         assert!(result.is_err());
-        assert!(result
-            .unwrap_err()
-            .contains("Failed to parse the message JSON"));
+        assert!(matches!(result, Err(FlyPathError::Parse(_))));
         fs::remove_file(file_path).unwrap();
     }
 
@@ -376,6 +2042,22 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_get_rand_message_with_seed() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let messages = Messages::default();
+        let mode = &FlyPathModes::Spicy(FlyPathThemes::Batman);
+        let mut rng1 = StdRng::seed_from_u64(7);
+        let mut rng2 = StdRng::seed_from_u64(7);
+        for _ in 0..20 {
+            assert_eq!(
+                messages.get_rand_message_with(mode, "Crash", &mut rng1),
+                messages.get_rand_message_with(mode, "Crash", &mut rng2)
+            );
+        }
+    }
+
     #[test]
     fn test_generate_nodeEvent_to_controller() {
         // Case: Valid Test
@@ -426,9 +2108,16 @@ mod tests {
             let mode = &FlyPathModes::Spicy(FlyPathThemes::Batman);
             let event_or_command = Messages::drone_command_to_string(&DroneCommand::Crash);
             let result = messages.generate_droneEvent_to_controller(mode, event_or_command, 1);
-            assert!(result
-                .unwrap_err()
-                .contains("Failed to generate a message: Too Long"));
+            assert!(matches!(
+                result,
+                Err(FlyPathError::MessageTooLong { len: 129, max: 120 })
+            ));
+            // the catalog is valid, the drones send the message across several fragments
+            let report = messages.validate();
+            assert!(report.is_valid());
+            assert!(report
+                .warnings()
+                .any(|issue| matches!(issue, CatalogIssue::MultiFragment { len: 129, .. })));
 
             std::fs::remove_file(file_path).unwrap();
         }
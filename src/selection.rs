@@ -0,0 +1,222 @@
+use crate::messages::CatalogMessage;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// How a message is picked from a list of the catalog
+///
+/// A list uses its own `strategy`, otherwise the one of its theme (or of the brainrot section)
+/// in the `strategies` section of the catalog, otherwise `Weighted`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SelectionStrategy {
+    /// Every message with the same probability, the weights are ignored
+    Uniform,
+    /// Every message with a probability proportional to its weight, `1` if it is not given
+    #[default]
+    Weighted,
+    /// Every message once in a random order, then again in a new order. The weights are ignored
+    ShuffleBag,
+    /// The messages in the order of the catalog. The weights are ignored
+    RoundRobin,
+}
+
+/// The selection state of a drone: its language, the position of every round-robin list and the
+/// messages left in every shuffle bag. `Uniform` and `Weighted` lists have no state.
+///
+/// Lists are identified by theme and key, the state of a list is reset when its length changes,
+/// e.g. after a catalog reload.
+#[derive(Debug, Clone, Default)]
+pub struct MessageSelector {
+    language: Option<String>,
+    lists: HashMap<(Option<String>, String), ListState>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct ListState {
+    len: usize,
+    next: usize,
+    bag: Vec<usize>,
+    last: Option<usize>,
+}
+
+impl MessageSelector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A selector that picks the variants of `language`, e.g. `"it"`, see `Messages::get_messages_for_language`
+    pub fn with_language(language: &str) -> Self {
+        MessageSelector {
+            language: Some(language.to_string()),
+            ..Self::default()
+        }
+    }
+
+    /// The language of the selector, `None` uses the fallback language of the catalog
+    pub fn language(&self) -> Option<&str> {
+        self.language.as_deref()
+    }
+
+    // Index of the next message of the list `key` of `theme` (`None` for the brainrot section)
+    pub(crate) fn pick<R: Rng + ?Sized>(
+        &mut self,
+        theme: Option<&str>,
+        key: &str,
+        strategy: SelectionStrategy,
+        messages: &[CatalogMessage],
+        rng: &mut R,
+    ) -> usize {
+        if matches!(
+            strategy,
+            SelectionStrategy::Uniform | SelectionStrategy::Weighted
+        ) {
+            return pick_stateless(strategy, messages, rng);
+        }
+        let state = self
+            .lists
+            .entry((theme.map(str::to_string), key.to_string()))
+            .or_default();
+        if state.len != messages.len() {
+            *state = ListState {
+                len: messages.len(),
+                ..ListState::default()
+            };
+        }
+        let index = if strategy == SelectionStrategy::RoundRobin {
+            state.next % state.len
+        } else {
+            if state.bag.is_empty() {
+                state.bag = (0..state.len).collect();
+                state.bag.shuffle(rng);
+                // the first message of the new bag is never the last one of the previous bag
+                if state.len > 1 && state.bag.last() == state.last.as_ref() {
+                    state.bag.swap(0, state.len - 1);
+                }
+            }
+            state.bag.pop().unwrap_or(0)
+        };
+        state.next = index + 1;
+        state.last = Some(index);
+        index
+    }
+}
+
+// Index of a message of `messages` picked without a selector: `ShuffleBag` and `RoundRobin` pick uniformly
+pub(crate) fn pick_stateless<R: Rng + ?Sized>(
+    strategy: SelectionStrategy,
+    messages: &[CatalogMessage],
+    rng: &mut R,
+) -> usize {
+    let total: usize = messages.iter().map(|message| message.weight as usize).sum();
+    if strategy != SelectionStrategy::Weighted || total == 0 {
+        return rng.gen_range(0..messages.len());
+    }
+    let mut target = rng.gen_range(0..total);
+    for (index, message) in messages.iter().enumerate() {
+        if target < message.weight as usize {
+            return index;
+        }
+        target -= message.weight as usize;
+    }
+    messages.len() - 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn messages(weights: &[u32]) -> Vec<CatalogMessage> {
+        weights
+            .iter()
+            .enumerate()
+            .map(|(i, weight)| CatalogMessage {
+                text: format!("Message {}", i),
+                weight: *weight,
+                source: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_weighted() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let list = messages(&[0, 3, 1]);
+        let mut counts = [0; 3];
+        for _ in 0..4000 {
+            counts[pick_stateless(SelectionStrategy::Weighted, &list, &mut rng)] += 1;
+        }
+        assert_eq!(counts[0], 0);
+        assert!(counts[1] > 2 * counts[2]);
+    }
+
+    #[test]
+    fn test_round_robin() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut selector = MessageSelector::new();
+        let list = messages(&[1, 5, 1]);
+        let picked: Vec<usize> = (0..5)
+            .map(|_| {
+                selector.pick(
+                    None,
+                    "Crash",
+                    SelectionStrategy::RoundRobin,
+                    &list,
+                    &mut rng,
+                )
+            })
+            .collect();
+        assert_eq!(picked, vec![0, 1, 2, 0, 1]);
+
+        // every list has its own position
+        assert_eq!(
+            selector.pick(
+                Some("Pingu"),
+                "Crash",
+                SelectionStrategy::RoundRobin,
+                &list,
+                &mut rng
+            ),
+            0
+        );
+        // a list with a different length starts again
+        let longer = messages(&[1, 1, 1, 1]);
+        assert_eq!(
+            selector.pick(
+                None,
+                "Crash",
+                SelectionStrategy::RoundRobin,
+                &longer,
+                &mut rng
+            ),
+            0
+        );
+    }
+
+    #[test]
+    fn test_shuffle_bag() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut selector = MessageSelector::new();
+        let list = messages(&[1, 1, 1, 1]);
+        let picked: Vec<usize> = (0..40)
+            .map(|_| {
+                selector.pick(
+                    None,
+                    "Crash",
+                    SelectionStrategy::ShuffleBag,
+                    &list,
+                    &mut rng,
+                )
+            })
+            .collect();
+        for bag in picked.chunks(4) {
+            let mut bag = bag.to_vec();
+            bag.sort();
+            assert_eq!(bag, vec![0, 1, 2, 3]);
+        }
+        assert!(picked.windows(2).all(|pair| pair[0] != pair[1]));
+    }
+}
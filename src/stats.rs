@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+use std::sync::{Arc, PoisonError, RwLock};
+use wg_2024::network::NodeId;
+use wg_2024::packet::{NackType, PacketType};
+
+/// `NackType` without the node id, used as key of the generated nacks counters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NackKind {
+    ErrorInRouting,
+    DestinationIsDrone,
+    Dropped,
+    UnexpectedRecipient,
+}
+
+impl From<&NackType> for NackKind {
+    fn from(nack_type: &NackType) -> Self {
+        match nack_type {
+            NackType::ErrorInRouting(_) => NackKind::ErrorInRouting,
+            NackType::DestinationIsDrone => NackKind::DestinationIsDrone,
+            NackType::Dropped => NackKind::Dropped,
+            NackType::UnexpectedRecipient(_) => NackKind::UnexpectedRecipient,
+        }
+    }
+}
+
+/// Counters of the traffic of a drone, used both for the totals and for every single neighbor
+///
+/// # Fields
+/// - `fragments_forwarded`: fragments sent to the next hop
+/// - `fragments_dropped`: fragments dropped because of the pdr
+/// - `nacks_generated`: nacks created by the drone, for each `NackKind`
+/// - `acks_relayed`, `nacks_relayed`, `flood_responses_relayed`: packets of other nodes sent to the next hop
+/// - `controller_shortcuts`: packets sent to the controller because they could not reach the next hop
+/// - `flood_requests_forwarded`: flood requests sent to a neighbor, counted once for every neighbor
+/// - `flood_requests_answered`: flood requests answered with a `FloodResponse`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LinkStats {
+    pub fragments_forwarded: u64,
+    pub fragments_dropped: u64,
+    pub nacks_generated: HashMap<NackKind, u64>,
+    pub acks_relayed: u64,
+    pub nacks_relayed: u64,
+    pub flood_responses_relayed: u64,
+    pub controller_shortcuts: u64,
+    pub flood_requests_forwarded: u64,
+    pub flood_requests_answered: u64,
+}
+
+impl LinkStats {
+    /// Number of nacks of the given kind generated by the drone
+    pub fn nacks_generated(&self, kind: NackKind) -> u64 {
+        self.nacks_generated.get(&kind).copied().unwrap_or(0)
+    }
+}
+
+/// Traffic statistics of a `FlyPath` drone
+///
+/// # Fields
+/// - `total`: counters of the whole drone
+/// - `neighbors`: counters for every neighbor, the neighbor is the next hop for the packets sent by the drone
+///   and the previous hop for the dropped fragments and the answered flood requests
+/// - `brainrot_actions`: how many times every BrainRot action has been taken
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FlyPathStats {
+    pub total: LinkStats,
+    pub neighbors: HashMap<NodeId, LinkStats>,
+    pub brainrot_actions: HashMap<String, u64>,
+}
+
+impl FlyPathStats {
+    pub fn neighbor(&self, id: NodeId) -> Option<&LinkStats> {
+        self.neighbors.get(&id)
+    }
+}
+
+/// Shared handle to the statistics of a drone.
+///
+/// The handle can be cloned and sent to another thread, e.g. the simulation controller, before the drone
+/// is moved inside its own thread. `snapshot` returns a copy of the statistics at the moment of the call.
+#[derive(Debug, Clone, Default)]
+pub struct StatsHandle {
+    stats: Arc<RwLock<FlyPathStats>>,
+}
+
+impl StatsHandle {
+    pub fn snapshot(&self) -> FlyPathStats {
+        self.stats
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Fragments forwarded by the drone, without copying the rest of the statistics
+    pub fn fragments_forwarded(&self) -> u64 {
+        self.stats
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .total
+            .fragments_forwarded
+    }
+
+    // Apply `update` to the totals and, if present, to the counters of `neighbor`
+    pub(crate) fn record<F: Fn(&mut LinkStats)>(&self, neighbor: Option<NodeId>, update: F) {
+        let mut stats = self.stats.write().unwrap_or_else(PoisonError::into_inner);
+        update(&mut stats.total);
+        if let Some(neighbor) = neighbor {
+            update(stats.neighbors.entry(neighbor).or_default());
+        }
+    }
+
+    // Count a packet successfully sent to `next_hop` that was not created by the drone
+    pub(crate) fn record_relayed(&self, next_hop: NodeId, pack_type: &PacketType) {
+        self.record(Some(next_hop), |link| match pack_type {
+            PacketType::MsgFragment(_) => link.fragments_forwarded += 1,
+            PacketType::Ack(_) => link.acks_relayed += 1,
+            PacketType::Nack(_) => link.nacks_relayed += 1,
+            PacketType::FloodResponse(_) => link.flood_responses_relayed += 1,
+            PacketType::FloodRequest(_) => link.flood_requests_forwarded += 1,
+        });
+    }
+
+    pub(crate) fn record_nack_generated(&self, neighbor: Option<NodeId>, nack_type: &NackType) {
+        let kind = NackKind::from(nack_type);
+        self.record(neighbor, |link| {
+            *link.nacks_generated.entry(kind).or_insert(0) += 1;
+        });
+    }
+
+    #[cfg(feature = "modes")]
+    pub(crate) fn record_brainrot(&self, action: &str) {
+        let mut stats = self.stats.write().unwrap_or_else(PoisonError::into_inner);
+        *stats
+            .brainrot_actions
+            .entry(action.to_string())
+            .or_insert(0) += 1;
+    }
+}
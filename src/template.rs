@@ -0,0 +1,206 @@
+use wg_2024::controller::DroneCommand;
+use wg_2024::network::NodeId;
+use wg_2024::packet::{NackType, Packet, PacketType};
+
+/// Every placeholder that can be used inside a message of the catalog
+pub const PLACEHOLDERS: [&str; 8] = [
+    "node_id",
+    "neighbor",
+    "pdr",
+    "session_id",
+    "fragment_index",
+    "next_hop",
+    "nack_type",
+    "packets_forwarded",
+];
+
+/// Values of the placeholders of a message, `None` values are rendered as `?`.
+///
+/// A message of the catalog can contain placeholders such as `{node_id}` or `{nack_type}`, see
+/// `PLACEHOLDERS`. `{{` and `}}` are rendered as `{` and `}`; a placeholder that is not in
+/// `PLACEHOLDERS` or a brace without its pair is left as it is, and reported by `Messages::validate`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TemplateValues {
+    /// drone that sends the message
+    pub node_id: Option<NodeId>,
+    /// neighbor added or removed by the command
+    pub neighbor: Option<NodeId>,
+    /// pdr set by the command, otherwise the pdr of the drone
+    pub pdr: Option<f32>,
+    pub session_id: Option<u64>,
+    pub fragment_index: Option<u64>,
+    pub next_hop: Option<NodeId>,
+    pub nack_type: Option<NackType>,
+    /// fragments forwarded by the drone
+    pub packets_forwarded: Option<u64>,
+}
+
+impl TemplateValues {
+    /// Values of the command that triggered the message: `neighbor` and `pdr`
+    pub fn from_command(command: &DroneCommand) -> Self {
+        match command {
+            DroneCommand::AddSender(neighbor, _) | DroneCommand::RemoveSender(neighbor) => Self {
+                neighbor: Some(*neighbor),
+                ..Self::default()
+            },
+            DroneCommand::SetPacketDropRate(pdr) => Self {
+                pdr: Some(*pdr),
+                ..Self::default()
+            },
+            DroneCommand::Crash => Self::default(),
+        }
+    }
+
+    /// Values of the packet that triggered the message: `session_id`, `fragment_index` (of a fragment,
+    /// ack or nack), `nack_type` (of a nack) and `next_hop`, the hop after the current one
+    pub fn from_packet(packet: &Packet) -> Self {
+        let (fragment_index, nack_type) = match &packet.pack_type {
+            PacketType::MsgFragment(fragment) => (Some(fragment.fragment_index), None),
+            PacketType::Ack(ack) => (Some(ack.fragment_index), None),
+            PacketType::Nack(nack) => (Some(nack.fragment_index), Some(nack.nack_type)),
+            _ => (None, None),
+        };
+        Self {
+            session_id: Some(packet.session_id),
+            fragment_index,
+            next_hop: packet.routing_header.next_hop(),
+            nack_type,
+            ..Self::default()
+        }
+    }
+
+    // The rendered value of `placeholder`, `None` if it is not a placeholder
+    fn value(&self, placeholder: &str) -> Option<String> {
+        fn or_unknown<T: ToString>(value: &Option<T>) -> String {
+            value
+                .as_ref()
+                .map_or_else(|| "?".to_string(), ToString::to_string)
+        }
+        let value = match placeholder {
+            "node_id" => or_unknown(&self.node_id),
+            "neighbor" => or_unknown(&self.neighbor),
+            "pdr" => or_unknown(&self.pdr),
+            "session_id" => or_unknown(&self.session_id),
+            "fragment_index" => or_unknown(&self.fragment_index),
+            "next_hop" => or_unknown(&self.next_hop),
+            "nack_type" => self
+                .nack_type
+                .as_ref()
+                .map_or_else(|| "?".to_string(), |nack_type| format!("{:?}", nack_type)),
+            "packets_forwarded" => or_unknown(&self.packets_forwarded),
+            _ => return None,
+        };
+        Some(value)
+    }
+}
+
+/// Replace the placeholders of `template` with `values`, see `TemplateValues` for the rules
+pub(crate) fn render(template: &str, values: &TemplateValues) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(brace) = rest.find(['{', '}']) {
+        rendered.push_str(&rest[..brace]);
+        rest = &rest[brace..];
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            rendered.push_str(&rest[..1]);
+            rest = &rest[2..];
+            continue;
+        }
+        if rest.starts_with('{') {
+            if let Some(end) = rest.find('}') {
+                if let Some(value) = values.value(&rest[1..end]) {
+                    rendered.push_str(&value);
+                    rest = &rest[end + 1..];
+                    continue;
+                }
+            }
+        }
+        // a lone brace or an unknown placeholder is kept as it is
+        rendered.push_str(&rest[..1]);
+        rest = &rest[1..];
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+/// Check the placeholders and the braces of `template`
+///
+/// # Returns
+/// - `Err(String)`: the first problem found, e.g. an unknown placeholder or an unclosed brace
+pub(crate) fn check(template: &str) -> Result<(), String> {
+    let mut rest = template;
+    while let Some(brace) = rest.find(['{', '}']) {
+        rest = &rest[brace..];
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            rest = &rest[2..];
+            continue;
+        }
+        if rest.starts_with('}') {
+            return Err("'}' without '{', use '}}' for a brace".to_string());
+        }
+        let Some(end) = rest.find('}') else {
+            return Err("'{' without '}', use '{{' for a brace".to_string());
+        };
+        let placeholder = &rest[1..end];
+        if !PLACEHOLDERS.contains(&placeholder) {
+            return Err(format!("unknown placeholder {{{}}}", placeholder));
+        }
+        rest = &rest[end + 1..];
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wg_2024::network::SourceRoutingHeader;
+    use wg_2024::packet::Nack;
+
+    #[test]
+    fn test_render() {
+        let values = TemplateValues {
+            node_id: Some(1),
+            neighbor: Some(4),
+            ..TemplateValues::default()
+        };
+        assert_eq!(
+            render("Drone {node_id} lost {neighbor}", &values),
+            "Drone 1 lost 4"
+        );
+        assert_eq!(render("pdr: {pdr}", &values), "pdr: ?");
+        assert_eq!(render("{{node_id}} }} {{", &values), "{node_id} } {");
+        assert_eq!(render("{unknown} {node_id", &values), "{unknown} {node_id");
+        assert_eq!(render("Ünïcode {node_id}!", &values), "Ünïcode 1!");
+
+        let nack = Packet::new_nack(
+            SourceRoutingHeader::with_first_hop(vec![3, 1, 2]),
+            9,
+            Nack {
+                fragment_index: 5,
+                nack_type: NackType::ErrorInRouting(2),
+            },
+        );
+        assert_eq!(
+            render(
+                "{session_id}/{fragment_index} to {next_hop}: {nack_type}",
+                &TemplateValues::from_packet(&nack)
+            ),
+            "9/5 to 2: ErrorInRouting(2)"
+        );
+        assert_eq!(
+            render(
+                "{pdr}",
+                &TemplateValues::from_command(&DroneCommand::SetPacketDropRate(0.5))
+            ),
+            "0.5"
+        );
+    }
+
+    #[test]
+    fn test_check() {
+        assert!(check("Drone {node_id}, {{escaped}} and }}").is_ok());
+        assert!(check("Drone {node}").is_err());
+        assert!(check("Drone {node_id").is_err());
+        assert!(check("Drone }").is_err());
+    }
+}
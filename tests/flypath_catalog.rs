@@ -0,0 +1,121 @@
+#![cfg(feature = "modes")]
+
+use flyPath::Messages;
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+const FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/catalog.json");
+
+// Run `flypath-catalog` with `args` from a temporary directory, so that nothing depends on the working directory
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_flypath-catalog"))
+        .args(args)
+        .current_dir(std::env::temp_dir())
+        .output()
+        .expect("Failed to run flypath-catalog")
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn temp_file(name: &str, content: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("flypath_cli_{}_{}", std::process::id(), name));
+    fs::write(&path, content).unwrap();
+    path
+}
+
+#[test]
+fn test_lint() {
+    let output = run(&["lint", "--catalog", FIXTURE]);
+    assert!(output.status.success());
+    assert!(stdout(&output).contains(": 0 errors"));
+
+    let invalid = temp_file(
+        "invalid.json",
+        r#"{"spicy": {"Batman": {"Jump": ["Nope"]}}, "brainrot": {}}"#,
+    );
+    let output = run(&["lint", "--catalog", invalid.to_str().unwrap()]);
+    let _ = fs::remove_file(&invalid);
+    assert!(!output.status.success());
+    assert!(stdout(&output).contains("error: theme 'Batman' has unknown key 'Jump'"));
+
+    // without --catalog the embedded catalog is used
+    assert!(run(&["lint"]).status.success());
+}
+
+#[test]
+fn test_list_themes() {
+    let output = run(&["list-themes", "--catalog", FIXTURE]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "Batman\nStar Wars (custom)\n");
+}
+
+#[test]
+fn test_preview() {
+    let output = run(&[
+        "preview",
+        "Batman",
+        "RemoveSender",
+        "--count",
+        "2",
+        "--language",
+        "it",
+        "--catalog",
+        FIXTURE,
+    ]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "Dov'è 2?\nDov'è 2?\n");
+
+    let output = run(&["preview", "Batman", "PacketSent", "--catalog", FIXTURE]);
+    assert!(!output.status.success());
+    let output = run(&["preview", "Batman", "--catalog", FIXTURE]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_stats() {
+    let output = run(&["stats", "--catalog", FIXTURE]);
+    assert!(output.status.success());
+    let stdout = stdout(&output);
+    let star_wars = stdout
+        .lines()
+        .find(|line| line.starts_with("Star Wars"))
+        .unwrap();
+    // the filled PacketDropped message fits a fragment only without the header of the drone
+    assert_eq!(
+        star_wars.split_whitespace().collect::<Vec<_>>(),
+        vec!["Star", "Wars", "2/7", "2", "92", "1"]
+    );
+    assert!(stdout.contains(
+        "missing: PacketSent, ControllerShortcut, AddSender, RemoveSender, SetPacketDropRate"
+    ));
+}
+
+#[test]
+fn test_fmt() {
+    // the fixture is already formatted
+    let output = run(&["fmt", "--catalog", FIXTURE]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), fs::read_to_string(FIXTURE).unwrap());
+
+    // a TOML catalog is rewritten in canonical order and formatting it again changes nothing
+    let messages = Messages::load_from_file(FIXTURE).unwrap();
+    let toml = temp_file("catalog.toml", &toml::to_string(&messages).unwrap());
+    let path = toml.to_str().unwrap();
+    assert!(run(&["fmt", "--write", "--catalog", path]).status.success());
+    let formatted = fs::read_to_string(&toml).unwrap();
+    let output = run(&["fmt", "--catalog", path]);
+    let reloaded = Messages::load_from_file(path);
+    let _ = fs::remove_file(&toml);
+    assert_eq!(stdout(&output), formatted);
+    assert_eq!(
+        reloaded.unwrap().lines(),
+        messages.with_source(path).lines()
+    );
+
+    // the embedded catalog can be printed but not rewritten
+    assert!(run(&["fmt"]).status.success());
+    assert_eq!(run(&["fmt", "--write"]).status.code(), Some(2));
+}